@@ -1,5 +1,22 @@
 // import from .js file because im lazy to configure ts
-import init, { Boid, RollingAverage, Vec2, World } from "./pkg/boids.js";
+import * as wasm from "./pkg/boids.js";
+import { RollingAverage, World } from "./pkg/boids.js";
+import {
+    createWorld,
+    Currents,
+    PATH_RADIUS,
+    Pointer,
+    setBackend,
+    setCurrents,
+    setPath,
+    setPointer,
+    TickResult,
+    tickWorld,
+    WorkerRequest,
+    WorkerResponse,
+    WorldParam,
+    WorldSettings,
+} from "./simulation.js";
 
 let canvas = document.getElementById("canvas") as HTMLCanvasElement;
 let ctx = canvas.getContext("2d");
@@ -11,10 +28,20 @@ let debug = false;
 debugButton.addEventListener("click", () => {
     debug = !debug;
     debugButton.innerText = debug ? "Hide Debug" : "Show Debug";
+    treeStatsText.style.display = debug ? "block" : "none";
 });
 
 let tickMsText = document.getElementById("tick-ms") as HTMLParagraphElement;
 let renderMsText = document.getElementById("render-ms") as HTMLParagraphElement;
+let treeStatsText = document.getElementById(
+    "tree-stats"
+) as HTMLParagraphElement;
+let populationText = document.getElementById(
+    "population"
+) as HTMLParagraphElement;
+let populationCanvas = document.getElementById(
+    "population-chart"
+) as HTMLCanvasElement;
 
 let protectRangeSlider = document.getElementById(
     "protect-range"
@@ -35,10 +62,22 @@ let marginSlider = document.getElementById("margin") as HTMLInputElement;
 let turnFactorSlider = document.getElementById(
     "turn-factor"
 ) as HTMLInputElement;
+let wanderSlider = document.getElementById("wander") as HTMLInputElement;
+let noiseSlider = document.getElementById("noise") as HTMLInputElement;
+let falloffSelect = document.getElementById("falloff") as HTMLSelectElement;
+let backendSelect = document.getElementById("backend") as HTMLSelectElement;
+let pointerSelect = document.getElementById("pointer") as HTMLSelectElement;
+let currentsSelect = document.getElementById("currents") as HTMLSelectElement;
+let pathSelect = document.getElementById("path") as HTMLSelectElement;
+let lifecycleSelect = document.getElementById(
+    "lifecycle"
+) as HTMLSelectElement;
 
 const BOIDS_SIZE = 6;
 const INTERVAL_MS = 5;
 const AVG_WINDOW = 100;
+// ticks of population shown in the chart
+const POPULATION_WINDOW = 500;
 
 // world settings
 let numBoids = 5000;
@@ -51,6 +90,19 @@ let margin = 25;
 let turnFactor = 0.2;
 let maxSpeed = 4;
 let minSpeed = 1;
+let wanderStrength = 0;
+let noise = 0;
+let pointerRadius = 50;
+// lifecycle settings while it is on
+let maxAge = 3000;
+let birthRate = 0.0005;
+
+// set while the mouse is held down on the canvas
+let pointer: Pointer = undefined;
+// closed loop the boids follow as x, y pairs
+let path: Float64Array = undefined;
+// boids after each recent tick, oldest first
+let population: number[] = [];
 
 let intervalId: number = undefined;
 
@@ -64,6 +116,135 @@ class Duration {
     }
 }
 
+// the world either lives on this thread, or in a worker
+// running the threaded build when shared memory is available
+interface Simulation {
+    tick(): Promise<TickResult>;
+    set(name: WorldParam, value: number): void;
+    setBackend(grid: boolean): void;
+    setPointer(pointer: Pointer | undefined): void;
+    setCurrents(currents: Currents): void;
+    setPath(path: Float64Array | undefined): void;
+    // only the local world can be inspected
+    world(): World | undefined;
+}
+
+class LocalSimulation implements Simulation {
+    inner: World;
+    constructor(settings: WorldSettings, boids: Float64Array) {
+        this.inner = createWorld(wasm, settings, boids);
+    }
+    public tick(): Promise<TickResult> {
+        return Promise.resolve(tickWorld(this.inner));
+    }
+    public set(name: WorldParam, value: number) {
+        this.inner[name] = value;
+    }
+    public setBackend(grid: boolean) {
+        setBackend(wasm, this.inner, grid);
+    }
+    public setPointer(pointer: Pointer | undefined) {
+        setPointer(wasm, this.inner, pointer);
+    }
+    public setCurrents(currents: Currents) {
+        setCurrents(wasm, this.inner, currents);
+    }
+    public setPath(path: Float64Array | undefined) {
+        setPath(this.inner, path);
+    }
+    public world(): World | undefined {
+        return this.inner;
+    }
+}
+
+class WorkerSimulation implements Simulation {
+    worker: Worker;
+    pendingTick: (result: TickResult) => void = undefined;
+    constructor(worker: Worker) {
+        this.worker = worker;
+        this.worker.onmessage = (event: MessageEvent<WorkerResponse>) => {
+            let response = event.data;
+            if (response.type === "tick" && this.pendingTick) {
+                let resolve = this.pendingTick;
+                this.pendingTick = undefined;
+                resolve(response);
+            }
+        };
+    }
+    private send(request: WorkerRequest) {
+        this.worker.postMessage(request);
+    }
+    public tick(): Promise<TickResult> {
+        return new Promise((resolve) => {
+            this.pendingTick = resolve;
+            this.send({ type: "tick" });
+        });
+    }
+    public set(name: WorldParam, value: number) {
+        this.send({ type: "set", name, value });
+    }
+    public setBackend(grid: boolean) {
+        this.send({ type: "backend", grid });
+    }
+    public setPointer(pointer: Pointer | undefined) {
+        this.send({ type: "pointer", pointer });
+    }
+    public setCurrents(currents: Currents) {
+        this.send({ type: "currents", currents });
+    }
+    public setPath(path: Float64Array | undefined) {
+        this.send({ type: "path", path });
+    }
+    public world(): World | undefined {
+        return undefined;
+    }
+}
+
+function threadsAvailable(): boolean {
+    // SharedArrayBuffer only exists on cross origin isolated
+    // pages, which needs the headers set by serve.py
+    return (
+        typeof SharedArrayBuffer !== "undefined" &&
+        self.crossOriginIsolated === true
+    );
+}
+
+// resolves with undefined if the threaded world cannot start,
+// e.g. pkg-threads was not built
+function startWorker(
+    settings: WorldSettings,
+    boids: Float64Array
+): Promise<WorkerSimulation | undefined> {
+    return new Promise((resolve) => {
+        let worker: Worker;
+        try {
+            worker = new Worker("worker.js", { type: "module" });
+        } catch (err) {
+            console.warn("could not start simulation worker:", err);
+            resolve(undefined);
+            return;
+        }
+        worker.onerror = (event) => {
+            console.warn("simulation worker failed:", event.message);
+            worker.terminate();
+            resolve(undefined);
+        };
+        worker.onmessage = (event: MessageEvent<WorkerResponse>) => {
+            let response = event.data;
+            if (response.type === "ready") {
+                console.info(`ticking on ${response.threads} threads`);
+                resolve(new WorkerSimulation(worker));
+            } else if (response.type === "error") {
+                console.warn("simulation worker failed:", response.message);
+                worker.terminate();
+                resolve(undefined);
+            }
+        };
+        let request: WorkerRequest = { type: "init", settings, boids };
+        worker.postMessage(request);
+    });
+}
+
 function randRange(min: number, max: number) {
     return Math.random() * (max - min) + min;
 }
@@ -92,75 +273,172 @@ function hslToRgb(h: number, s: number, l: number): [number, number, number] {
 }
 
 // https://stackoverflow.com/questions/17525215/calculate-color-values-from-green-to-red/17527156#17527156
-function speedToColor(speed: Vec2): string {
+function speedToColor(vx: number, vy: number): string {
     // slower boids are more red
     // faster boids are more green
-    let speedMag = speed.length();
+    let speedMag = Math.hypot(vx, vy);
     let hue = ((speedMag - minSpeed) / (maxSpeed - minSpeed)) * 120;
     let [r, g, b] = hslToRgb(hue / 360, 1, 0.5);
     return `rgb(${r}, ${g}, ${b})`;
 }
 
-function drawBoids(world: World) {
+function showTreeStats(world: World | undefined) {
+    if (world === undefined) {
+        treeStatsText.innerText = "Quadtree stats unavailable in worker";
+        return;
+    }
+    let stats = world.quadtree_stats();
+    if (stats === undefined) {
+        treeStatsText.innerText = "Quadtree inactive";
+        return;
+    }
+    let histogram: number[] = [];
+    for (let depth = 0; depth < stats.depths(); depth++) {
+        histogram.push(stats.items_at_depth(depth));
+    }
+    treeStatsText.innerText = [
+        `Nodes: ${stats.node_count}`,
+        `Leaves: ${stats.leaf_count}`,
+        `Max depth: ${stats.max_depth}`,
+        `Items/leaf: ${stats.avg_leaf_items.toFixed(2)}`,
+        `Items/depth: ${histogram.join(" ")}`,
+    ].join("\n");
+    stats.free();
+}
+
+function showPopulation(result: TickResult) {
+    population.push(result.boids.length / 4);
+    if (population.length > POPULATION_WINDOW) {
+        population.shift();
+    }
+    populationText.innerText = [
+        `Population: ${population[population.length - 1]}`,
+        `Births: ${result.births}`,
+        `Deaths: ${result.deaths}`,
+    ].join("\n");
+
+    // line chart scaled to the largest population in the window
+    let chart = populationCanvas.getContext("2d");
+    let [width, height] = [populationCanvas.width, populationCanvas.height];
+    let most = Math.max(...population, 1);
+    chart.fillStyle = "white";
+    chart.fillRect(0, 0, width, height);
+    chart.strokeStyle = "black";
+    chart.lineWidth = 1;
+    chart.beginPath();
+    population.forEach((count, i) => {
+        let x = (i / (POPULATION_WINDOW - 1)) * width;
+        let y = height - (count / most) * height;
+        if (i === 0) {
+            chart.moveTo(x, y);
+        } else {
+            chart.lineTo(x, y);
+        }
+    });
+    chart.stroke();
+}
+
+// ellipse around the middle of the canvas
+function loopPath(points: number): Float64Array {
+    let path = new Float64Array(points * 2);
+    for (let i = 0; i < points; i++) {
+        let angle = (i / points) * Math.PI * 2;
+        path[i * 2] = canvas.width / 2 + Math.cos(angle) * canvas.width * 0.35;
+        path[i * 2 + 1] =
+            canvas.height / 2 + Math.sin(angle) * canvas.height * 0.35;
+    }
+    return path;
+}
+
+// one line per grid node, pointing along the current
+function drawFlowField(world: World | undefined) {
+    if (world === undefined) {
+        return;
+    }
+    let cols = world.flow_field_cols();
+    let rows = world.flow_field_rows();
+    if (cols === 0) {
+        return;
+    }
+    let data = world.flow_field_data();
+    let dims = world.dims;
+    let [spacingX, spacingY] = [dims.x / (cols - 1), dims.y / (rows - 1)];
+    dims.free();
+    ctx.strokeStyle = "gray";
+    ctx.lineWidth = 1;
+    ctx.beginPath();
+    for (let row = 0; row < rows; row++) {
+        for (let col = 0; col < cols; col++) {
+            let i = (row * cols + col) * 2;
+            let [x, y] = [col * spacingX, row * spacingY];
+            ctx.moveTo(x, y);
+            ctx.lineTo(x + data[i] * 50, y + data[i + 1] * 50);
+        }
+    }
+    ctx.stroke();
+}
+
+function drawBoids(boids: Float64Array) {
     ctx.fillStyle = "black";
     ctx.fillRect(0, 0, canvas.width, canvas.height);
-    for (let i = 0; i < numBoids; i++) {
-        let boid = world.get_boid(i);
-        // cache pos and vel to avoid repeated WASM allocations
-        let pos = boid.pos;
-        let vel = boid.vel;
-
-        let vel_norm = vel.normalize();
-        let vel_dir = vel_norm.mul_num(BOIDS_SIZE);
-        vel_norm.free();
-
-        let rot1 = vel_dir.rotate((Math.PI * 11) / 12);
-        let bot_left = rot1.add_vec(pos);
-        rot1.free();
-
-        let rot2 = vel_dir.rotate((Math.PI * 13) / 12);
-        let bot_right = rot2.add_vec(pos);
-        rot2.free();
-        vel_dir.free();
-
-        ctx.fillStyle = speedToColor(vel);
+    for (let i = 0; i < boids.length; i += 4) {
+        let [x, y, vx, vy] = boids.subarray(i, i + 4);
+
+        // triangle pointing along the velocity
+        let angle = Math.atan2(vy, vx);
+        let leftAngle = angle + (Math.PI * 11) / 12;
+        let rightAngle = angle + (Math.PI * 13) / 12;
+
+        ctx.fillStyle = speedToColor(vx, vy);
         ctx.beginPath();
-        ctx.moveTo(pos.x, pos.y);
-        ctx.lineTo(bot_left.x, bot_left.y);
-        ctx.lineTo(bot_right.x, bot_right.y);
+        ctx.moveTo(x, y);
+        ctx.lineTo(
+            x + Math.cos(leftAngle) * BOIDS_SIZE,
+            y + Math.sin(leftAngle) * BOIDS_SIZE
+        );
+        ctx.lineTo(
+            x + Math.cos(rightAngle) * BOIDS_SIZE,
+            y + Math.sin(rightAngle) * BOIDS_SIZE
+        );
         ctx.fill();
+    }
 
-        bot_left.free();
-        bot_right.free();
-        vel.free();
-        pos.free();
-        boid.free();
+    if (path !== undefined) {
+        ctx.strokeStyle = "rgba(255, 255, 255, 0.2)";
+        ctx.lineWidth = PATH_RADIUS * 2;
+        ctx.beginPath();
+        ctx.moveTo(path[0], path[1]);
+        for (let i = 2; i < path.length; i += 2) {
+            ctx.lineTo(path[i], path[i + 1]);
+        }
+        ctx.closePath();
+        ctx.stroke();
+    }
+
+    if (pointer !== undefined) {
+        ctx.strokeStyle = "white";
+        ctx.lineWidth = 1;
+        ctx.beginPath();
+        ctx.arc(pointer.x, pointer.y, pointerRadius, 0, Math.PI * 2);
+        ctx.stroke();
     }
 
     if (debug) {
         // draw protected range
         ctx.strokeStyle = "red";
         ctx.lineWidth = 1;
-        for (let i = 0; i < world.num_boids(); i++) {
-            let boid = world.get_boid(i);
-            let pos = boid.pos;
+        for (let i = 0; i < boids.length; i += 4) {
             ctx.beginPath();
-            ctx.arc(pos.x, pos.y, world.protect_range, 0, Math.PI * 2);
+            ctx.arc(boids[i], boids[i + 1], protectedRange, 0, Math.PI * 2);
             ctx.stroke();
-            pos.free();
-            boid.free();
         }
 
         // draw visible range
         ctx.strokeStyle = "blue";
-        for (let i = 0; i < world.num_boids(); i++) {
-            let boid = world.get_boid(i);
-            let pos = boid.pos;
+        for (let i = 0; i < boids.length; i += 4) {
             ctx.beginPath();
-            ctx.arc(pos.x, pos.y, world.visible_range, 0, Math.PI * 2);
+            ctx.arc(boids[i], boids[i + 1], visibleRange, 0, Math.PI * 2);
             ctx.stroke();
-            pos.free();
-            boid.free();
         }
 
         // draw margins
@@ -168,23 +446,24 @@ function drawBoids(world: World) {
         ctx.setLineDash([5, 5]);
         ctx.beginPath();
         ctx.rect(
-            world.margin,
-            world.margin,
-            canvas.width - world.margin * 2,
-            canvas.height - world.margin * 2
+            margin,
+            margin,
+            canvas.width - margin * 2,
+            canvas.height - margin * 2
         );
         ctx.stroke();
+        ctx.setLineDash([]);
     }
 }
 
-init().then(() => {
+wasm.default().then(async () => {
     // wasm initialized
     canvas.width = window.outerWidth;
     canvas.height = window.outerHeight;
-    let dims = Vec2.new(canvas.width, canvas.height);
 
-    let world = World.new(
-        dims,
+    let settings: WorldSettings = {
+        width: canvas.width,
+        height: canvas.height,
         visibleRange,
         protectedRange,
         avoidFactor,
@@ -193,56 +472,126 @@ init().then(() => {
         margin,
         turnFactor,
         maxSpeed,
-        minSpeed
-    );
+        minSpeed,
+    };
+    let boids = new Float64Array(numBoids * 4);
+    for (let i = 0; i < boids.length; i += 4) {
+        let angle = randRange(0, Math.PI * 2);
+        let speed = randRange(minSpeed, maxSpeed);
+        boids[i] = randRange(0, canvas.width);
+        boids[i + 1] = randRange(0, canvas.height);
+        boids[i + 2] = Math.cos(angle) * speed;
+        boids[i + 3] = Math.sin(angle) * speed;
+    }
 
-    for (let i = 0; i < numBoids; i++) {
-        world.add_boid(
-            Vec2.js_rand_01().mul_vec(dims),
-            Vec2.js_rand_01()
-                .mul_num(2)
-                .sub_num(1)
-                .normalize()
-                .mul_num(randRange(minSpeed, maxSpeed))
-        );
+    // fall back to ticking on this thread
+    // when the threaded build is not usable
+    let sim: Simulation = undefined;
+    if (threadsAvailable()) {
+        sim = await startWorker(settings, boids.slice());
+    }
+    if (sim === undefined) {
+        sim = new LocalSimulation(settings, boids);
     }
-    drawBoids(world);
+    drawBoids(boids);
 
     // param event listeners
     protectRangeSlider.value = protectedRange.toString();
     protectRangeSlider.addEventListener("input", () => {
         protectedRange = parseInt(protectRangeSlider.value);
-        world.protect_range = protectedRange;
+        sim.set("protect_range", protectedRange);
     });
     visibleRangeSlider.value = visibleRange.toString();
     visibleRangeSlider.addEventListener("input", () => {
         visibleRange = parseInt(visibleRangeSlider.value);
-        world.visible_range = visibleRange;
+        sim.set("visible_range", visibleRange);
     });
     avoidFactorSlider.value = (avoidFactor * 100).toString();
     avoidFactorSlider.addEventListener("input", () => {
         avoidFactor = parseFloat(avoidFactorSlider.value) / 100;
-        world.avoid_factor = avoidFactor;
+        sim.set("avoid_factor", avoidFactor);
     });
     alignFactorSlider.value = (alignFactor * 100).toString();
     alignFactorSlider.addEventListener("input", () => {
         alignFactor = parseFloat(alignFactorSlider.value) / 100;
-        world.align_factor = alignFactor;
+        sim.set("align_factor", alignFactor);
     });
     cohesionFactorSlider.value = (cohesionFactor * 100_000).toString();
     cohesionFactorSlider.addEventListener("input", () => {
         cohesionFactor = parseFloat(cohesionFactorSlider.value) / 100_000;
-        world.cohesion_factor = cohesionFactor;
+        sim.set("cohesion_factor", cohesionFactor);
     });
     marginSlider.value = margin.toString();
     marginSlider.addEventListener("input", () => {
         margin = parseInt(marginSlider.value);
-        world.margin = margin;
+        sim.set("margin", margin);
     });
     turnFactorSlider.value = (turnFactor * 10).toString();
     turnFactorSlider.addEventListener("input", () => {
         turnFactor = parseFloat(turnFactorSlider.value) / 10;
-        world.turn_factor = turnFactor;
+        sim.set("turn_factor", turnFactor);
+    });
+    wanderSlider.value = (wanderStrength * 100).toString();
+    wanderSlider.addEventListener("input", () => {
+        wanderStrength = parseFloat(wanderSlider.value) / 100;
+        sim.set("wander_strength", wanderStrength);
+    });
+    noiseSlider.value = (noise * 100).toString();
+    noiseSlider.addEventListener("input", () => {
+        noise = parseFloat(noiseSlider.value) / 100;
+        sim.set("noise", noise);
+    });
+    falloffSelect.addEventListener("change", () => {
+        let falloffs: Record<string, wasm.SeparationFalloff> = {
+            linear: wasm.SeparationFalloff.Linear,
+            inverse: wasm.SeparationFalloff.Inverse,
+            "inverse-square": wasm.SeparationFalloff.InverseSquare,
+            smooth: wasm.SeparationFalloff.Smooth,
+        };
+        sim.set("separation_falloff", falloffs[falloffSelect.value]);
+    });
+    backendSelect.addEventListener("change", () => {
+        sim.setBackend(backendSelect.value === "grid");
+    });
+
+    // pointer interaction, the world applies it every tick
+    sim.set("pointer_radius", pointerRadius);
+    let movePointer = (event: MouseEvent) => {
+        pointer = {
+            x: event.offsetX,
+            y: event.offsetY,
+            mode: pointerSelect.value as Pointer["mode"],
+        };
+        sim.setPointer(pointer);
+    };
+    let releasePointer = () => {
+        pointer = undefined;
+        sim.setPointer(undefined);
+    };
+    canvas.addEventListener("mousedown", movePointer);
+    canvas.addEventListener("mousemove", (event) => {
+        if (pointer !== undefined) {
+            movePointer(event);
+        }
+    });
+    canvas.addEventListener("mouseup", releasePointer);
+    canvas.addEventListener("mouseleave", releasePointer);
+
+    currentsSelect.addEventListener("change", () => {
+        sim.setCurrents(currentsSelect.value as Currents);
+    });
+    pathSelect.addEventListener("change", () => {
+        path = pathSelect.value === "loop" ? loopPath(48) : undefined;
+        sim.setPath(path);
+    });
+    lifecycleSelect.addEventListener("change", () => {
+        let on = lifecycleSelect.value === "on";
+        sim.set("max_age", on ? maxAge : 0);
+        sim.set("birth_rate", on ? birthRate : 0);
+        population = [];
+        let display = on ? "block" : "none";
+        populationText.style.display = display;
+        populationCanvas.style.display = display;
     });
 
     let avgTick = RollingAverage.new(AVG_WINDOW);
@@ -253,18 +602,35 @@ init().then(() => {
             intervalId = undefined;
             playButton.innerText = "Play";
         } else {
-            intervalId = setInterval(() => {
+            let ticking = false;
+            intervalId = setInterval(async () => {
+                // a worker tick can take longer than the interval
+                if (ticking) return;
+                ticking = true;
+
                 let start = new Duration();
-                world.tick();
+                let result = await sim.tick();
+                let boids = result.boids;
                 avgTick.push(start.elapsed_ms());
                 tickMsText.innerText = `Tick ms: ${avgTick.query().toFixed(1)}`;
 
                 start = new Duration();
-                drawBoids(world);
+                drawBoids(boids);
+                if (debug) {
+                    drawFlowField(sim.world());
+                }
                 avgRender.push(start.elapsed_ms());
                 renderMsText.innerText = `Render ms: ${avgRender
                     .query()
                     .toFixed(1)}`;
+
+                if (debug) {
+                    showTreeStats(sim.world());
+                }
+                if (lifecycleSelect.value === "on") {
+                    showPopulation(result);
+                }
+                ticking = false;
             }, INTERVAL_MS);
             playButton.innerText = "Pause";
         }
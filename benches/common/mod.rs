@@ -0,0 +1,24 @@
+use boids::Vec2;
+
+/**
+xorshift64, fixed seed so every run and every
+commit measures the same inputs
+ */
+pub struct Rng(u64);
+impl Rng {
+    pub fn new() -> Self {
+        Self(0x5eed_b01d)
+    }
+    pub fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+    /**
+    uniform point in the rectangle from 0 to dims
+     */
+    pub fn next_pos(&mut self, dims: Vec2) -> Vec2 {
+        Vec2::new(self.next_f64(), self.next_f64()) * dims
+    }
+}
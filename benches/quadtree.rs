@@ -0,0 +1,67 @@
+use std::hint::black_box;
+
+use boids::{Vec2, WasmQuadTree};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+mod common;
+use common::Rng;
+
+// every tree covers the same area, more
+// points means a denser tree
+const DIMS: f64 = 1000.0;
+const NUM_QUERIES: usize = 1000;
+
+fn random_points(num_points: usize, rng: &mut Rng) -> Vec<Vec2> {
+    (0..num_points)
+        .map(|_| rng.next_pos(Vec2::from(DIMS)))
+        .collect()
+}
+
+fn new_tree(points: &[Vec2]) -> WasmQuadTree {
+    let mut tree = WasmQuadTree::new(&Vec2::from(DIMS));
+    for point in points {
+        tree.push(point);
+    }
+    tree
+}
+
+fn bench_push(c: &mut Criterion) {
+    let mut group = c.benchmark_group("quadtree_push");
+    for num_points in [1_000, 10_000, 100_000] {
+        let points = random_points(num_points, &mut Rng::new());
+        group.throughput(Throughput::Elements(num_points as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(num_points),
+            &points,
+            |b, points| b.iter(|| new_tree(points)),
+        );
+    }
+    group.finish();
+}
+
+fn bench_query_circle(c: &mut Criterion) {
+    let mut group = c.benchmark_group("quadtree_query_circle");
+    group.throughput(Throughput::Elements(NUM_QUERIES as u64));
+    for num_points in [1_000, 10_000, 100_000] {
+        let mut rng = Rng::new();
+        let tree = new_tree(&random_points(num_points, &mut rng));
+        let centers = random_points(NUM_QUERIES, &mut rng);
+        for radius in [8.0, 32.0, 128.0] {
+            group.bench_with_input(
+                BenchmarkId::new(format!("{num_points}_points"), radius),
+                &radius,
+                |b, &radius| {
+                    b.iter(|| {
+                        for center in &centers {
+                            black_box(tree.query_circle(center, radius));
+                        }
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_push, bench_query_circle);
+criterion_main!(benches);
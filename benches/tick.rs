@@ -0,0 +1,99 @@
+use boids::{SpatialBackend, Vec2, World};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+mod common;
+use common::Rng;
+
+/**
+same parameters and density as app.ts,
+5000 boids on a 1920x1080 canvas
+ */
+fn new_world(num_boids: usize) -> World {
+    let area_per_boid = 1920.0 * 1080.0 / 5000.0;
+    let side = (num_boids as f64 * area_per_boid).sqrt();
+    let dims = Vec2::from(side);
+    let mut world = World::new(&dims, 32.0, 8.0, 0.05, 0.05, 0.0005, 25.0, 0.2, 4.0, 1.0);
+
+    let mut rng = Rng::new();
+    for _ in 0..num_boids {
+        let pos = rng.next_pos(dims);
+        let angle = rng.next_f64() * std::f64::consts::TAU;
+        let speed = 1.0 + rng.next_f64() * 3.0;
+        world.add_boid(&pos, &(Vec2::new(speed, 0.0).rotate(angle)));
+    }
+    // let the flock settle into groups first
+    for _ in 0..20 {
+        world.tick();
+    }
+    world
+}
+
+/**
+run with and without --features parallel to compare
+ */
+fn bench_tick(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tick");
+    group.sample_size(10);
+    for num_boids in [10_000, 25_000, 50_000, 100_000] {
+        let mut world = new_world(num_boids);
+        group.throughput(Throughput::Elements(num_boids as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(num_boids),
+            &num_boids,
+            |b, _| b.iter(|| world.tick()),
+        );
+    }
+    group.finish();
+}
+
+/**
+the spatial index against checking every pair
+ */
+fn bench_tick_vs_brute_force(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tick_vs_brute_force");
+    group.sample_size(10);
+    for num_boids in [100, 1_000, 10_000] {
+        group.throughput(Throughput::Elements(num_boids as u64));
+        let mut world = new_world(num_boids);
+        group.bench_with_input(BenchmarkId::new("tick", num_boids), &num_boids, |b, _| {
+            b.iter(|| world.tick())
+        });
+        let mut world = new_world(num_boids);
+        group.bench_with_input(
+            BenchmarkId::new("brute_force", num_boids),
+            &num_boids,
+            |b, _| b.iter(|| world.tick_brute_force()),
+        );
+    }
+    group.finish();
+}
+
+/**
+clearing and refilling the spatial index,
+which tick does once per frame
+ */
+fn bench_rebuild(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rebuild");
+    for num_boids in [1_000, 10_000, 100_000] {
+        group.throughput(Throughput::Elements(num_boids as u64));
+        let mut world = new_world(num_boids);
+        for (name, backend) in [
+            ("quadtree", SpatialBackend::QuadTree),
+            ("spatial_hash", SpatialBackend::SpatialHash),
+        ] {
+            world.set_backend(backend);
+            group.bench_with_input(BenchmarkId::new(name, num_boids), &num_boids, |b, _| {
+                b.iter(|| world.bench_rebuild())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_tick,
+    bench_tick_vs_brute_force,
+    bench_rebuild
+);
+criterion_main!(benches);
@@ -0,0 +1,61 @@
+use std::process::exit;
+
+use boids::optimize::{Fitness, Optimizer, Scenario};
+
+const USAGE: &str = "usage: optimize <polarization|collisions|clusters|target> \
+[--generations N] [--population N] [--boids N] [--runs N] [--seed N]";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}\n{USAGE}");
+    exit(1);
+}
+
+/**
+evolves avoid_factor, align_factor and cohesion_factor for a
+fitness and prints the best config as json, progress goes to
+stderr. cargo run --release --example optimize -- polarization
+ */
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let scenario = Scenario::default();
+    let fitness = match args.next().as_deref() {
+        Some("polarization") => Fitness::Polarization,
+        // boids closer than half the protected range
+        Some("collisions") => Fitness::Collisions {
+            radius: scenario.protect_range / 2.0,
+        },
+        Some("clusters") => Fitness::Clusters { target: 1 },
+        Some("target") => Fitness::TargetDistance {
+            target: scenario.dims / 2.0,
+        },
+        Some(other) => fail(&format!("unknown fitness {other}")),
+        None => fail("missing fitness"),
+    };
+    let mut optimizer = Optimizer::new(scenario, fitness);
+
+    while let Some(flag) = args.next() {
+        let value: u64 = match args.next().map(|v| v.parse()) {
+            Some(Ok(value)) => value,
+            _ => fail(&format!("{flag} needs a number")),
+        };
+        match flag.as_str() {
+            "--generations" => optimizer.generations = value as usize,
+            "--population" => optimizer.population = value as usize,
+            "--boids" => optimizer.scenario.num_boids = value as usize,
+            "--runs" => optimizer.scenario.runs = value as usize,
+            "--seed" => optimizer.seed = value,
+            _ => fail(&format!("unknown flag {flag}")),
+        }
+    }
+
+    let best = optimizer.run(|generation, best| {
+        eprintln!(
+            "generation {generation}: score {:.4}, avoid {:.4}, align {:.4}, cohesion {:.6}",
+            best.score,
+            best.genome.avoid_factor,
+            best.genome.align_factor,
+            best.genome.cohesion_factor,
+        );
+    });
+    println!("{}", best.to_json(fitness));
+}
@@ -1,5 +1,10 @@
 // must import from .js file, not .ts file
-import init, { Vec2, WasmQuadTree, WasmVec2Array } from "./pkg/boids.js";
+import init, {
+    Vec2,
+    WasmQuadTree,
+    WasmRayHit,
+    WasmVec2Array,
+} from "./pkg/boids.js";
 
 let canvas = document.getElementById("canvas") as HTMLCanvasElement;
 let ctx = canvas.getContext("2d");
@@ -16,6 +21,7 @@ let clearPointsButton = document.getElementById(
 
 const POINT_RADIUS = 3;
 const DRAG_DIST = 20;
+const NEAREST_K = 3;
 
 function isDigit(key: string) {
     return key.length == 1 && "0" <= key && key <= "9";
@@ -57,9 +63,21 @@ function drawTreeRecur(
         );
     });
 }
+type Obstacle = { x: number; y: number; radius: number };
+let obstacles: Obstacle[] = [];
+
+function drawObstacles() {
+    ctx.fillStyle = "lightgray";
+    for (let obstacle of obstacles) {
+        ctx.beginPath();
+        ctx.arc(obstacle.x, obstacle.y, obstacle.radius, 0, Math.PI * 2);
+        ctx.fill();
+    }
+}
 function drawTree(tree: WasmQuadTree) {
     ctx.clearRect(0, 0, canvas.width, canvas.height);
-    drawTreeRecur(tree, 0, Vec2.zero(), tree.dims());
+    drawObstacles();
+    drawTreeRecur(tree, 0, tree.top_left(), tree.dims());
 }
 function drawCirclePoints(center: Vec2, radius: number, points: WasmVec2Array) {
     ctx.fillStyle = "green";
@@ -83,6 +101,47 @@ function drawCirclePoints(center: Vec2, radius: number, points: WasmVec2Array) {
     ctx.stroke();
 }
 
+function drawRay(origin: Vec2, end: Vec2, hit: WasmRayHit | undefined) {
+    ctx.strokeStyle = "purple";
+    ctx.lineWidth = 2;
+    ctx.beginPath();
+    ctx.moveTo(origin.x, origin.y);
+    if (hit === undefined) {
+        ctx.lineTo(end.x, end.y);
+        ctx.stroke();
+        return;
+    }
+    let dir = end.sub_vec(origin).normalize();
+    let hitPos = origin.add_vec(dir.mul_num(hit.dist));
+    ctx.lineTo(hitPos.x, hitPos.y);
+    ctx.stroke();
+
+    // outline whatever was hit, point or obstacle
+    let item = hit.item;
+    ctx.beginPath();
+    ctx.arc(item.x, item.y, hit.radius + POINT_RADIUS, 0, Math.PI * 2);
+    ctx.stroke();
+    item.free();
+    hitPos.free();
+    dir.free();
+}
+
+function drawNearestPoints(cursor: Vec2, points: WasmVec2Array) {
+    ctx.strokeStyle = "orange";
+    ctx.lineWidth = 1;
+    for (let i = 0; i < points.len(); i++) {
+        let pos = points.get(i);
+        ctx.beginPath();
+        ctx.moveTo(cursor.x, cursor.y);
+        ctx.lineTo(pos.x, pos.y);
+        ctx.stroke();
+        ctx.beginPath();
+        ctx.arc(pos.x, pos.y, POINT_RADIUS * 2, 0, Math.PI * 2);
+        ctx.stroke();
+        pos.free();
+    }
+}
+
 init().then(() => {
     // wasm initialized
     canvas.width = window.innerWidth;
@@ -103,6 +162,37 @@ init().then(() => {
         drawCirclePoints(startPos, radius, points);
     }
 
+    function windowObstacle(startPos: Vec2, endPos: Vec2) {
+        let radius = startPos.distance(endPos);
+        if (tree.add_obstacle(startPos, radius)) {
+            obstacles.push({ x: startPos.x, y: startPos.y, radius });
+        }
+        drawTree(tree);
+    }
+
+    function windowRay(startPos: Vec2, endPos: Vec2) {
+        let dir = endPos.sub_vec(startPos);
+        let hit = tree.raycast(startPos, dir, dir.length(), POINT_RADIUS);
+        drawTree(tree);
+        drawRay(startPos, endPos, hit);
+        hit?.free();
+        dir.free();
+    }
+
+    function windowHover(pos: Vec2) {
+        let points = tree.k_nearest(pos, NEAREST_K);
+        drawTree(tree);
+        drawNearestPoints(pos, points);
+        points.free();
+    }
+
+    window.addEventListener("mousemove", (event) => {
+        // dont overwrite the circle query while dragging
+        if (event.buttons !== 0) return;
+        let pos = Vec2.new(event.clientX, event.clientY);
+        windowHover(pos);
+        pos.free();
+    });
     window.addEventListener("mousedown", (event) => {
         mouseDownPos = Vec2.new(event.clientX, event.clientY);
     });
@@ -110,6 +200,12 @@ init().then(() => {
         let mouseUpPos = Vec2.new(event.clientX, event.clientY);
         if (mouseDownPos.distance(mouseUpPos) < DRAG_DIST) {
             windowClick(mouseUpPos);
+        } else if (event.shiftKey) {
+            // shift drag casts a ray instead of a circle query
+            windowRay(mouseDownPos, mouseUpPos);
+        } else if (event.altKey) {
+            // alt drag places an obstacle that blocks rays
+            windowObstacle(mouseDownPos, mouseUpPos);
         } else {
             windowDrag(mouseDownPos, mouseUpPos);
         }
@@ -135,6 +231,7 @@ init().then(() => {
     });
     clearPointsButton.addEventListener("click", () => {
         tree.clear();
+        obstacles = [];
         totalPointsText.innerText = `Total points: ${tree.len()}`;
         inputCache = 0;
         inputText.innerText = `Add points: ${inputCache}`;
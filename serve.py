@@ -0,0 +1,14 @@
+# same as python3 -m http.server, plus the headers that make the
+# page cross origin isolated so SharedArrayBuffer is available
+import http.server
+
+
+class IsolatedHandler(http.server.SimpleHTTPRequestHandler):
+    def end_headers(self):
+        self.send_header("Cross-Origin-Opener-Policy", "same-origin")
+        self.send_header("Cross-Origin-Embedder-Policy", "require-corp")
+        super().end_headers()
+
+
+if __name__ == "__main__":
+    http.server.test(HandlerClass=IsolatedHandler)
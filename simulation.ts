@@ -0,0 +1,179 @@
+// shared between the main thread and the simulation worker
+import type * as Boids from "./pkg/boids.js";
+
+export type WasmModule = typeof Boids;
+
+// numeric World fields the sliders can change
+export type WorldParam =
+    | "protect_range"
+    | "visible_range"
+    | "avoid_factor"
+    | "align_factor"
+    | "cohesion_factor"
+    | "margin"
+    | "turn_factor"
+    | "pointer_radius"
+    | "wander_strength"
+    | "noise"
+    | "separation_falloff"
+    | "max_age"
+    | "birth_rate";
+
+// where the pointer is held down and what it does there
+export interface Pointer {
+    x: number;
+    y: number;
+    mode: "attract" | "repel" | "paint" | "erase";
+}
+
+export interface WorldSettings {
+    width: number;
+    height: number;
+    visibleRange: number;
+    protectedRange: number;
+    avoidFactor: number;
+    alignFactor: number;
+    cohesionFactor: number;
+    margin: number;
+    turnFactor: number;
+    maxSpeed: number;
+    minSpeed: number;
+}
+
+// environmental currents added to every boid's velocity
+export type Currents = "none" | "wind" | "curl";
+
+// messages sent to the worker
+export type WorkerRequest =
+    | { type: "init"; settings: WorldSettings; boids: Float64Array }
+    | { type: "tick" }
+    | { type: "set"; name: WorldParam; value: number }
+    | { type: "backend"; grid: boolean }
+    | { type: "pointer"; pointer: Pointer | undefined }
+    | { type: "currents"; currents: Currents }
+    | { type: "path"; path: Float64Array | undefined };
+
+// boids after a tick as x, y, vx, vy, and how
+// many were born and died during it
+export interface TickResult {
+    boids: Float64Array;
+    births: number;
+    deaths: number;
+}
+
+// messages sent back by the worker
+export type WorkerResponse =
+    | { type: "ready"; threads: number }
+    | { type: "error"; message: string }
+    | ({ type: "tick" } & TickResult);
+
+// boids are passed around as x, y, vx, vy
+// in a flat array, same as World.boid_data
+export function createWorld(
+    wasm: WasmModule,
+    settings: WorldSettings,
+    boids: Float64Array
+): Boids.World {
+    let dims = wasm.Vec2.new(settings.width, settings.height);
+    let world = wasm.World.new(
+        dims,
+        settings.visibleRange,
+        settings.protectedRange,
+        settings.avoidFactor,
+        settings.alignFactor,
+        settings.cohesionFactor,
+        settings.margin,
+        settings.turnFactor,
+        settings.maxSpeed,
+        settings.minSpeed
+    );
+    dims.free();
+    // boid_data stays in insertion order, so sorting the
+    // flock for cache locality is invisible from here
+    world.reorder_interval = 16;
+
+    for (let i = 0; i < boids.length; i += 4) {
+        let pos = wasm.Vec2.new(boids[i], boids[i + 1]);
+        let vel = wasm.Vec2.new(boids[i + 2], boids[i + 3]);
+        world.add_boid(pos, vel);
+        pos.free();
+        vel.free();
+    }
+    return world;
+}
+
+export function setBackend(
+    wasm: WasmModule,
+    world: Boids.World,
+    grid: boolean
+) {
+    world.set_backend(
+        grid ? wasm.SpatialBackend.SpatialHash : wasm.SpatialBackend.QuadTree
+    );
+}
+
+// undefined releases the pointer
+export function setPointer(
+    wasm: WasmModule,
+    world: Boids.World,
+    pointer: Pointer | undefined
+) {
+    if (pointer === undefined) {
+        world.clear_pointer();
+        return;
+    }
+    let modes = {
+        attract: wasm.PointerMode.Attract,
+        repel: wasm.PointerMode.Repel,
+        paint: wasm.PointerMode.Paint,
+        erase: wasm.PointerMode.Erase,
+    };
+    let pos = wasm.Vec2.new(pointer.x, pointer.y);
+    world.set_pointer(pos, modes[pointer.mode]);
+    pos.free();
+}
+
+// grid nodes of the flow field are this many pixels apart
+const FLOW_SPACING = 40;
+
+export function setCurrents(
+    wasm: WasmModule,
+    world: Boids.World,
+    currents: Currents
+) {
+    world.wind =
+        currents === "wind" ? wasm.Vec2.new(0.3, 0.1) : wasm.Vec2.zero();
+    if (currents === "curl") {
+        let dims = world.dims;
+        world.set_curl_noise_field(
+            Math.ceil(dims.x / FLOW_SPACING) + 1,
+            Math.ceil(dims.y / FLOW_SPACING) + 1,
+            0.003,
+            0.3,
+            1
+        );
+        dims.free();
+    } else {
+        world.clear_flow_field();
+    }
+}
+
+// closed loop of x, y pairs boids follow, undefined clears it
+export function setPath(world: Boids.World, path: Float64Array | undefined) {
+    if (path === undefined) {
+        world.clear_path();
+    } else {
+        world.set_path(path, true, PATH_RADIUS);
+    }
+}
+
+export const PATH_RADIUS = 30;
+
+export function tickWorld(world: Boids.World): TickResult {
+    world.tick();
+    return {
+        boids: world.boid_data(),
+        births: world.births(),
+        deaths: world.deaths(),
+    };
+}
@@ -0,0 +1,81 @@
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::vec2::Vec2;
+
+/**
+how the pull of an attractor weakens
+from its center to its radius
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Falloff {
+    // full strength everywhere inside the radius
+    Constant,
+    // full strength at the center, nothing at the radius
+    Linear,
+    // like linear, but eases in and out at both ends
+    Smooth,
+}
+impl Falloff {
+    /**
+    weight at dist / radius, t is in [0, 1)
+     */
+    pub fn weight(&self, t: f64) -> f64 {
+        match self {
+            Falloff::Constant => 1.0,
+            Falloff::Linear => 1.0 - t,
+            Falloff::Smooth => {
+                let s = 1.0 - t * t;
+                s * s
+            }
+        }
+    }
+}
+
+/**
+point that steers boids within its radius towards
+it, or away from it when strength is negative
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attractor {
+    pub pos: Vec2,
+    pub strength: f64,
+    pub radius: f64,
+    pub falloff: Falloff,
+}
+impl Attractor {
+    pub fn new(pos: Vec2, strength: f64, radius: f64, falloff: Falloff) -> Self {
+        Self {
+            pos,
+            strength,
+            radius,
+            falloff,
+        }
+    }
+    /**
+    steering on a boid at pos, zero outside the radius
+     */
+    pub fn force(&self, pos: Vec2) -> Vec2 {
+        let diff = self.pos - pos;
+        let dist = diff.length();
+        // a boid on the center has no direction to go
+        if dist >= self.radius || dist == 0.0 {
+            return Vec2::zero();
+        }
+        diff / dist * (self.strength * self.falloff.weight(dist / self.radius))
+    }
+}
+
+#[test]
+fn test_attractor_force() {
+    let attractor = Attractor::new(Vec2::new(10.0, 10.0), 2.0, 5.0, Falloff::Constant);
+    assert_eq!(attractor.force(Vec2::new(7.0, 10.0)), Vec2::new(2.0, 0.0));
+    assert_eq!(attractor.force(Vec2::new(10.0, 15.0)), Vec2::zero());
+    assert_eq!(attractor.force(Vec2::new(10.0, 10.0)), Vec2::zero());
+
+    let repeller = Attractor::new(Vec2::new(10.0, 10.0), -2.0, 5.0, Falloff::Linear);
+    assert_eq!(repeller.force(Vec2::new(10.0, 12.5)), Vec2::new(0.0, 1.0));
+
+    let smooth = Attractor::new(Vec2::zero(), 1.0, 2.0, Falloff::Smooth);
+    assert_eq!(smooth.force(Vec2::new(0.0, 1.0)), Vec2::new(0.0, -0.5625));
+}
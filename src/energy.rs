@@ -0,0 +1,71 @@
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::vec2::Vec2;
+
+/**
+patch that gives energy to every boid inside it
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Food {
+    pub pos: Vec2,
+    pub radius: f64,
+    // energy per tick to each boid inside
+    pub rate: f64,
+}
+impl Food {
+    pub fn new(pos: Vec2, radius: f64, rate: f64) -> Self {
+        Self { pos, radius, rate }
+    }
+    pub fn contains(&self, pos: Vec2) -> bool {
+        self.pos.distance_squared(&pos) <= self.radius * self.radius
+    }
+}
+
+/**
+population and energy of the flock after the last tick
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnergyStats {
+    pub population: usize,
+    // boids removed for running out of energy in the last tick
+    pub starved: usize,
+    // all 0 without boids
+    pub min: f64,
+    pub mean: f64,
+    pub max: f64,
+}
+impl EnergyStats {
+    pub fn new(energies: &[f64], starved: usize) -> Self {
+        if energies.is_empty() {
+            return Self {
+                population: 0,
+                starved,
+                min: 0.0,
+                mean: 0.0,
+                max: 0.0,
+            };
+        }
+        Self {
+            population: energies.len(),
+            starved,
+            min: energies.iter().copied().fold(f64::INFINITY, f64::min),
+            mean: energies.iter().sum::<f64>() / energies.len() as f64,
+            max: energies.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+#[test]
+fn test_energy_stats() {
+    let food = Food::new(Vec2::new(10.0, 10.0), 5.0, 1.0);
+    assert!(food.contains(Vec2::new(13.0, 14.0)));
+    assert!(!food.contains(Vec2::new(14.0, 14.0)));
+
+    let stats = EnergyStats::new(&[4.0, 1.0, 7.0], 2);
+    assert_eq!(stats.population, 3);
+    assert_eq!(stats.starved, 2);
+    assert_eq!((stats.min, stats.mean, stats.max), (1.0, 4.0, 7.0));
+    let empty = EnergyStats::new(&[], 0);
+    assert_eq!((empty.population, empty.min, empty.max), (0, 0.0, 0.0));
+}
@@ -0,0 +1,141 @@
+use crate::{params::Overrides, vec2::Vec2, world::Boid};
+
+/**
+boids stored as one array per component,
+so the neighbour loops read contiguous memory
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Flock {
+    pub xs: Vec<f64>,
+    pub ys: Vec<f64>,
+    pub vxs: Vec<f64>,
+    pub vys: Vec<f64>,
+    // id of the boid in each slot, ids do
+    // not change when the flock is reordered
+    pub ids: Vec<usize>,
+    // angle on the wander circle of each boid
+    pub wanders: Vec<f64>,
+    // steering is divided by mass, heavier boids turn slower
+    pub masses: Vec<f64>,
+    // boids burn energy as they move and starve at 0
+    pub energies: Vec<f64>,
+    // ticks since the boid was added
+    pub ages: Vec<usize>,
+    // most boids share every parameter with the world
+    pub overrides: Vec<Option<Box<Overrides>>>,
+    // whether the boid is one of the world's leaders
+    pub leaders: Vec<bool>,
+}
+impl Flock {
+    pub fn len(&self) -> usize {
+        self.xs.len()
+    }
+    pub fn push(&mut self, id: usize, boid: Boid) {
+        self.xs.push(boid.pos.x);
+        self.ys.push(boid.pos.y);
+        self.vxs.push(boid.vel.x);
+        self.vys.push(boid.vel.y);
+        self.ids.push(id);
+        self.wanders.push(0.0);
+        self.masses.push(1.0);
+        self.energies.push(0.0);
+        self.ages.push(0);
+        self.overrides.push(None);
+        self.leaders.push(false);
+    }
+    pub fn pos(&self, i: usize) -> Vec2 {
+        Vec2::new(self.xs[i], self.ys[i])
+    }
+    pub fn vel(&self, i: usize) -> Vec2 {
+        Vec2::new(self.vxs[i], self.vys[i])
+    }
+    pub fn get(&self, i: usize) -> Boid {
+        Boid::new(self.pos(i), self.vel(i))
+    }
+    pub fn set(&mut self, i: usize, boid: Boid) {
+        self.xs[i] = boid.pos.x;
+        self.ys[i] = boid.pos.y;
+        self.vxs[i] = boid.vel.x;
+        self.vys[i] = boid.vel.y;
+    }
+    pub fn set_pos(&mut self, i: usize, pos: Vec2) {
+        self.xs[i] = pos.x;
+        self.ys[i] = pos.y;
+    }
+    /**
+    removes the boid in slot i, the last
+    boid takes its place
+     */
+    pub fn swap_remove(&mut self, i: usize) {
+        self.xs.swap_remove(i);
+        self.ys.swap_remove(i);
+        self.vxs.swap_remove(i);
+        self.vys.swap_remove(i);
+        self.ids.swap_remove(i);
+        self.wanders.swap_remove(i);
+        self.masses.swap_remove(i);
+        self.energies.swap_remove(i);
+        self.ages.swap_remove(i);
+        self.overrides.swap_remove(i);
+        self.leaders.swap_remove(i);
+    }
+    /**
+    moves the boid in slot order[i] to slot i
+     */
+    pub fn permute(&mut self, order: &[usize]) {
+        debug_assert_eq!(order.len(), self.len());
+        self.xs = order.iter().map(|&i| self.xs[i]).collect();
+        self.ys = order.iter().map(|&i| self.ys[i]).collect();
+        self.vxs = order.iter().map(|&i| self.vxs[i]).collect();
+        self.vys = order.iter().map(|&i| self.vys[i]).collect();
+        self.ids = order.iter().map(|&i| self.ids[i]).collect();
+        self.wanders = order.iter().map(|&i| self.wanders[i]).collect();
+        self.masses = order.iter().map(|&i| self.masses[i]).collect();
+        self.energies = order.iter().map(|&i| self.energies[i]).collect();
+        self.ages = order.iter().map(|&i| self.ages[i]).collect();
+        self.leaders = order.iter().map(|&i| self.leaders[i]).collect();
+        // moved rather than cloned, each slot is taken once
+        let mut overrides = std::mem::take(&mut self.overrides);
+        self.overrides = order.iter().map(|&i| overrides[i].take()).collect();
+    }
+}
+
+#[test]
+fn test_flock() {
+    let mut flock = Flock::default();
+    flock.push(0, Boid::new(Vec2::new(1.0, 2.0), Vec2::new(3.0, 4.0)));
+    flock.push(1, Boid::new(Vec2::new(5.0, 6.0), Vec2::new(7.0, 8.0)));
+    assert_eq!(flock.len(), 2);
+    assert_eq!(flock.xs, vec![1.0, 5.0]);
+    assert_eq!(flock.vys, vec![4.0, 8.0]);
+    assert_eq!(flock.vel(1), Vec2::new(7.0, 8.0));
+
+    flock.set_pos(0, Vec2::new(-1.0, -2.0));
+    assert_eq!(flock.get(0).pos, Vec2::new(-1.0, -2.0));
+    assert_eq!(flock.get(0).vel, Vec2::new(3.0, 4.0));
+
+    flock.push(2, Boid::new(Vec2::new(9.0, 10.0), Vec2::zero()));
+    flock.wanders[2] = 0.5;
+    flock.masses[0] = 2.0;
+    flock.energies[1] = 3.0;
+    flock.ages[2] = 7;
+    flock.overrides[2] = Some(Box::default());
+    flock.leaders[1] = true;
+    flock.permute(&[2, 0, 1]);
+    assert_eq!(flock.ids, vec![2, 0, 1]);
+    assert_eq!(flock.wanders, vec![0.5, 0.0, 0.0]);
+    assert_eq!(flock.masses, vec![1.0, 2.0, 1.0]);
+    assert_eq!(flock.energies, vec![0.0, 0.0, 3.0]);
+    assert_eq!(flock.ages, vec![7, 0, 0]);
+    assert_eq!(flock.leaders, vec![false, false, true]);
+    assert!(flock.overrides[0].is_some());
+    assert!(flock.overrides[1].is_none());
+    assert_eq!(flock.xs, vec![9.0, -1.0, 5.0]);
+    assert_eq!(flock.vel(1), Vec2::new(3.0, 4.0));
+
+    flock.swap_remove(0);
+    assert_eq!(flock.ids, vec![1, 0]);
+    assert_eq!(flock.xs, vec![5.0, -1.0]);
+    assert_eq!(flock.leaders, vec![true, false]);
+    assert!(flock.overrides.iter().all(Option::is_none));
+}
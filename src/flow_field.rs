@@ -0,0 +1,196 @@
+use crate::{rng::Rng, vec2::Vec2};
+
+/**
+grid of vectors over a rectangle, sampled with
+bilinear interpolation between the grid nodes
+ */
+#[derive(Clone, Debug)]
+pub struct FlowField {
+    top_left: Vec2,
+    // distance between neighbouring nodes
+    spacing: Vec2,
+    cols: usize,
+    rows: usize,
+    // row major
+    vectors: Vec<Vec2>,
+}
+impl FlowField {
+    /**
+    None if there are fewer than 2 nodes per axis or data
+    is not cols * rows vectors as x, y pairs
+     */
+    pub fn from_slice(
+        top_left: Vec2,
+        dims: Vec2,
+        cols: usize,
+        rows: usize,
+        data: &[f64],
+    ) -> Option<Self> {
+        if cols < 2 || rows < 2 || data.len() != cols * rows * 2 {
+            return None;
+        }
+        Some(Self {
+            top_left,
+            spacing: Vec2::new(dims.x / (cols - 1) as f64, dims.y / (rows - 1) as f64),
+            cols,
+            rows,
+            vectors: data
+                .chunks_exact(2)
+                .map(|v| Vec2::new(v[0], v[1]))
+                .collect(),
+        })
+    }
+    /**
+    curl of perlin noise, which swirls without sinks or sources
+    so boids are not all dragged to the same spot. scale is the
+    noise frequency per unit, the longest vector has length strength
+     */
+    pub fn curl_noise(
+        top_left: Vec2,
+        dims: Vec2,
+        cols: usize,
+        rows: usize,
+        scale: f64,
+        strength: f64,
+        seed: u64,
+    ) -> Option<Self> {
+        let mut field = Self::from_slice(top_left, dims, cols, rows, &vec![0.0; cols * rows * 2])?;
+        let noise = Perlin::new(seed);
+        // central differences a fraction of a noise cell apart
+        let eps = 1e-3;
+        let mut longest: f64 = 0.0;
+        for row in 0..rows {
+            for col in 0..cols {
+                let pos = field.node_pos(col, row) * scale;
+                let dx = noise.sample(pos + Vec2::new(eps, 0.0))
+                    - noise.sample(pos - Vec2::new(eps, 0.0));
+                let dy = noise.sample(pos + Vec2::new(0.0, eps))
+                    - noise.sample(pos - Vec2::new(0.0, eps));
+                let curl = Vec2::new(dy, -dx) / (2.0 * eps);
+                longest = longest.max(curl.length());
+                field.vectors[row * cols + col] = curl;
+            }
+        }
+        if longest > 0.0 {
+            for v in &mut field.vectors {
+                *v = *v * (strength / longest);
+            }
+        }
+        Some(field)
+    }
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+    /**
+    every node vector as x, y pairs, row major
+     */
+    pub fn data(&self) -> Vec<f64> {
+        self.vectors.iter().flat_map(|v| [v.x, v.y]).collect()
+    }
+    fn node_pos(&self, col: usize, row: usize) -> Vec2 {
+        self.top_left + Vec2::new(col as f64, row as f64) * self.spacing
+    }
+    /**
+    positions outside the grid take the value at the nearest edge
+     */
+    pub fn sample(&self, pos: Vec2) -> Vec2 {
+        // cell and offset inside it along one axis
+        let locate = |offset: f64, spacing: f64, nodes: usize| {
+            let f = (offset / spacing).clamp(0.0, (nodes - 1) as f64);
+            let cell = (f as usize).min(nodes - 2);
+            (cell, f - cell as f64)
+        };
+        let (col, tx) = locate(pos.x - self.top_left.x, self.spacing.x, self.cols);
+        let (row, ty) = locate(pos.y - self.top_left.y, self.spacing.y, self.rows);
+        let at = |col: usize, row: usize| self.vectors[row * self.cols + col];
+        let top = at(col, row) * (1.0 - tx) + at(col + 1, row) * tx;
+        let bot = at(col, row + 1) * (1.0 - tx) + at(col + 1, row + 1) * tx;
+        top * (1.0 - ty) + bot * ty
+    }
+}
+
+/**
+2d gradient noise, roughly in [-1, 1]
+ */
+struct Perlin {
+    perm: [u8; 256],
+}
+impl Perlin {
+    fn new(seed: u64) -> Self {
+        let mut perm = [0; 256];
+        for (i, p) in perm.iter_mut().enumerate() {
+            *p = i as u8;
+        }
+        // fisher yates
+        let mut rng = Rng::new(seed);
+        for i in (1..perm.len()).rev() {
+            let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+            perm.swap(i, j);
+        }
+        Self { perm }
+    }
+    fn gradient(&self, x: i64, y: i64) -> Vec2 {
+        let hash = self.perm[(self.perm[x as u8 as usize] as usize + y as u8 as usize) % 256];
+        Vec2::new(1.0, 0.0).rotate(hash as f64 * std::f64::consts::TAU / 256.0)
+    }
+    fn sample(&self, pos: Vec2) -> f64 {
+        let (x0, y0) = (pos.x.floor(), pos.y.floor());
+        let (tx, ty) = (pos.x - x0, pos.y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let corner = |cx: i64, cy: i64| {
+            let offset = Vec2::new(tx - (cx - x0) as f64, ty - (cy - y0) as f64);
+            self.gradient(cx, cy).dot(&offset)
+        };
+        // smootherstep, so the noise has a continuous derivative
+        let fade = |t: f64| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let (u, v) = (fade(tx), fade(ty));
+        let top = corner(x0, y0) * (1.0 - u) + corner(x0 + 1, y0) * u;
+        let bot = corner(x0, y0 + 1) * (1.0 - u) + corner(x0 + 1, y0 + 1) * u;
+        top * (1.0 - v) + bot * v
+    }
+}
+
+#[test]
+fn test_flow_field_sample() {
+    // 3x2 nodes 10 apart, each vector is the node position
+    let data = [
+        0.0, 0.0, 10.0, 0.0, 20.0, 0.0, 0.0, 10.0, 10.0, 10.0, 20.0, 10.0,
+    ];
+    let field = FlowField::from_slice(Vec2::zero(), Vec2::new(20.0, 10.0), 3, 2, &data).unwrap();
+    assert_eq!(field.cols(), 3);
+    assert_eq!(field.rows(), 2);
+    assert_eq!(field.data(), data.to_vec());
+    // bilinear reproduces a linear field
+    assert_eq!(field.sample(Vec2::new(5.0, 5.0)), Vec2::new(5.0, 5.0));
+    assert_eq!(field.sample(Vec2::new(17.5, 2.5)), Vec2::new(17.5, 2.5));
+    assert_eq!(field.sample(Vec2::new(20.0, 10.0)), Vec2::new(20.0, 10.0));
+    // clamped outside
+    assert_eq!(field.sample(Vec2::new(-5.0, 30.0)), Vec2::new(0.0, 10.0));
+
+    assert!(FlowField::from_slice(Vec2::zero(), Vec2::from(10.0), 3, 2, &data[..10]).is_none());
+    assert!(FlowField::from_slice(Vec2::zero(), Vec2::from(10.0), 1, 6, &data).is_none());
+}
+
+#[test]
+fn test_flow_field_curl_noise() {
+    let new_field = |seed| {
+        FlowField::curl_noise(Vec2::zero(), Vec2::from(100.0), 21, 21, 0.05, 2.0, seed).unwrap()
+    };
+    let field = new_field(1);
+    assert_eq!(field.data(), new_field(1).data());
+    assert_ne!(field.data(), new_field(2).data());
+
+    let longest = field.vectors.iter().map(|v| v.length()).fold(0.0, f64::max);
+    assert!((longest - 2.0).abs() < 1e-9);
+
+    // curl has no divergence, checked with central
+    // differences between the nodes around the middle
+    let (c, r) = (10, 10);
+    let at = |col: usize, row: usize| field.vectors[row * field.cols + col];
+    let div = (at(c + 1, r).x - at(c - 1, r).x) + (at(c, r + 1).y - at(c, r - 1).y);
+    let spread = (at(c + 1, r) - at(c - 1, r)).length() + (at(c, r + 1) - at(c, r - 1)).length();
+    assert!(div.abs() < spread * 0.2 + 1e-9);
+}
@@ -0,0 +1,129 @@
+use crate::{vec2::Vec2, world::Boid};
+
+/**
+boid that the boids around it follow, optionally
+steering along a scripted list of waypoints
+ */
+#[derive(Clone)]
+pub struct Leader {
+    pub id: usize,
+    // state at the start of the tick, so every
+    // follower sees the same leader
+    pub boid: Boid,
+    pub waypoints: Vec<Vec2>,
+    pub looped: bool,
+    next_waypoint: usize,
+}
+impl Leader {
+    pub fn new(id: usize, boid: Boid) -> Self {
+        Self {
+            id,
+            boid,
+            waypoints: Vec::new(),
+            looped: false,
+            next_waypoint: 0,
+        }
+    }
+    pub fn set_path(&mut self, waypoints: Vec<Vec2>, looped: bool) {
+        self.waypoints = waypoints;
+        self.looped = looped;
+        self.next_waypoint = 0;
+    }
+    /**
+    moves on to the next waypoint once the leader is within
+    radius of the current one, an open path ends at its last
+     */
+    pub fn advance(&mut self, radius: f64) {
+        if self
+            .target()
+            .is_some_and(|target| self.boid.pos.distance(&target) <= radius)
+        {
+            self.next_waypoint += 1;
+            if self.looped {
+                self.next_waypoint %= self.waypoints.len();
+            }
+        }
+    }
+    /**
+    waypoint the leader is heading for, None
+    without a path or past the end of one
+     */
+    pub fn target(&self) -> Option<Vec2> {
+        self.waypoints.get(self.next_waypoint).copied()
+    }
+    /**
+    steering for a follower at pos towards the point distance
+    behind the leader, and sideways out of the way while it
+    is in the leader's path
+     */
+    pub fn follow(&self, pos: Vec2, distance: f64) -> Vec2 {
+        let speed = self.boid.vel.length();
+        if speed == 0.0 {
+            // no front or back, stay close
+            return self.boid.pos - pos;
+        }
+        let heading = self.boid.vel / speed;
+        let behind = self.boid.pos - heading * distance;
+        let mut steer = behind - pos;
+
+        let rel = pos - self.boid.pos;
+        let ahead = rel.dot(&heading);
+        let lateral = rel - heading * ahead;
+        let off_path = lateral.length();
+        if ahead > 0.0 && ahead < distance * 2.0 && off_path < distance {
+            // a follower right on the path steps to the left
+            let side = if off_path > 0.0 {
+                lateral / off_path
+            } else {
+                heading.rotate(-std::f64::consts::FRAC_PI_2)
+            };
+            steer += side * (distance - off_path);
+        }
+        steer
+    }
+}
+
+#[test]
+fn test_leader_advance() {
+    let mut leader = Leader::new(0, Boid::new(Vec2::zero(), Vec2::zero()));
+    assert_eq!(leader.target(), None);
+    leader.advance(1.0);
+
+    let path = vec![Vec2::new(0.5, 0.0), Vec2::new(10.0, 0.0)];
+    leader.set_path(path.clone(), false);
+    leader.advance(1.0);
+    assert_eq!(leader.target(), Some(Vec2::new(10.0, 0.0)));
+    leader.advance(1.0);
+    assert_eq!(leader.target(), Some(Vec2::new(10.0, 0.0)));
+    leader.boid.pos = Vec2::new(9.5, 0.0);
+    leader.advance(1.0);
+    assert_eq!(leader.target(), None);
+
+    leader.set_path(path, true);
+    leader.advance(1.0);
+    leader.advance(1.0);
+    // back to the start of the loop
+    assert_eq!(leader.target(), Some(Vec2::new(0.5, 0.0)));
+}
+
+#[test]
+fn test_leader_follow() {
+    let leader = Leader::new(0, Boid::new(Vec2::new(10.0, 10.0), Vec2::new(2.0, 0.0)));
+    // behind and off the path, only seeks the point behind
+    assert_eq!(
+        leader.follow(Vec2::new(0.0, 20.0), 5.0),
+        Vec2::new(5.0, -10.0)
+    );
+    // in front of the leader, also pushed sideways
+    assert_eq!(
+        leader.follow(Vec2::new(12.0, 12.0), 5.0),
+        Vec2::new(-7.0, 1.0)
+    );
+    // right on the path steps aside
+    let steer = leader.follow(Vec2::new(12.0, 10.0), 5.0);
+    assert_eq!(steer.x, -7.0);
+    assert!(steer.y.abs() > 4.9);
+
+    let still = Leader::new(0, Boid::new(Vec2::new(10.0, 10.0), Vec2::zero()));
+    assert_eq!(still.follow(Vec2::new(7.0, 6.0), 5.0), Vec2::new(3.0, 4.0));
+}
@@ -1,14 +1,50 @@
 use std::collections::VecDeque;
 
 use js_sys::Int32Array;
-use quadtree::QuadTree;
-use vec2::Vec2;
+use quadtree::{QuadTree, QuadTreeStats};
+pub use vec2::{Position, Vec2};
 use wasm_bindgen::prelude::wasm_bindgen;
 
+mod attractor;
+mod energy;
+mod flock;
+mod flow_field;
+mod leader;
+mod loose_quadtree;
+mod neighbours;
+// tunes the rule weights in headless worlds, native only
+#[cfg(not(target_arch = "wasm32"))]
+pub mod optimize;
+mod params;
+mod path;
 mod quadtree;
+mod rng;
+mod spatial_hash;
+mod spatial_index;
 mod vec2;
 mod world;
 
+pub use attractor::Falloff;
+pub use energy::EnergyStats;
+pub use loose_quadtree::{Bounded, Circle, LooseQuadTree};
+pub use neighbours::SeparationFalloff;
+pub use params::BoidParam;
+pub use world::{PointerMode, SpatialBackend, World};
+
+// exported to js as initThreadPool, which has to be awaited
+// before the first tick of a threaded build
+#[cfg(all(target_arch = "wasm32", feature = "wasm-threads"))]
+pub use wasm_bindgen_rayon::init_thread_pool;
+
+/**
+whether this build ticks on a thread pool,
+false means initThreadPool does not exist
+ */
+#[wasm_bindgen]
+pub fn threads_enabled() -> bool {
+    cfg!(all(target_arch = "wasm32", feature = "wasm-threads"))
+}
+
 /**
 Wrapper class around Vec<Vec2> for WASM
  */
@@ -26,6 +62,9 @@ impl WasmVec2Array {
     pub fn len(&self) -> usize {
         self.arr.len()
     }
+    pub fn is_empty(&self) -> bool {
+        self.arr.is_empty()
+    }
     pub fn get(&self, idx: usize) -> Vec2 {
         if idx >= self.len() {
             return Vec2::from(-1.0);
@@ -34,34 +73,119 @@ impl WasmVec2Array {
     }
 }
 
+/**
+Result of WasmQuadTree::raycast
+ */
+#[wasm_bindgen]
+pub struct WasmRayHit {
+    // center of the point or obstacle hit
+    pub item: Vec2,
+    pub radius: f64,
+    pub dist: f64,
+}
+
 /**
 Wrapper class around QuadTree<Vec2> for WASM
  */
 #[wasm_bindgen]
 pub struct WasmQuadTree {
     tree: QuadTree<Vec2>,
+    // borders restored by clear, an expanding tree grows past them
+    initial_dims: Vec2,
+    // circles that block rays, kept apart from the points
+    obstacles: LooseQuadTree<Circle>,
 }
 #[wasm_bindgen]
 impl WasmQuadTree {
     pub fn new(dims: &Vec2) -> Self {
         Self {
             tree: QuadTree::new(Vec2::zero(), *dims),
+            initial_dims: *dims,
+            obstacles: LooseQuadTree::new(Vec2::zero(), *dims),
         }
     }
     pub fn len(&self) -> usize {
         self.tree.len()
     }
+    pub fn is_empty(&self) -> bool {
+        self.tree.len() == 0
+    }
+    pub fn top_left(&self) -> Vec2 {
+        self.tree.border_top_left
+    }
     pub fn dims(&self) -> Vec2 {
         self.tree.border_dims
     }
-    pub fn push(&mut self, point: &Vec2) {
-        self.tree.push(point);
+    pub fn auto_expand(&self) -> bool {
+        self.tree.auto_expand()
+    }
+    /**
+    when set, points outside the borders grow
+    the tree instead of being dropped
+     */
+    pub fn set_auto_expand(&mut self, auto_expand: bool) {
+        self.tree.set_auto_expand(auto_expand);
+    }
+    /**
+    returns false if the point was not inserted
+     */
+    pub fn push(&mut self, point: &Vec2) -> bool {
+        self.tree.push(point)
     }
     pub fn query_circle(&self, center: &Vec2, radius: f64) -> WasmVec2Array {
         WasmVec2Array::new(self.tree.query_circle(*center, radius))
     }
+    pub fn nearest(&self, point: &Vec2) -> Option<Vec2> {
+        self.tree.nearest(*point)
+    }
+    pub fn k_nearest(&self, point: &Vec2, k: usize) -> WasmVec2Array {
+        WasmVec2Array::new(self.tree.k_nearest(*point, k))
+    }
+    /**
+    returns false if the obstacle's center is outside the
+    initial borders, its radius may extend past them
+     */
+    pub fn add_obstacle(&mut self, center: &Vec2, radius: f64) -> bool {
+        self.obstacles.push(&Circle {
+            center: *center,
+            radius,
+        })
+    }
+    pub fn num_obstacles(&self) -> usize {
+        self.obstacles.len()
+    }
+    /**
+    first point or obstacle hit by the ray, points are
+    circles of the given radius and obstacles keep their own
+     */
+    pub fn raycast(
+        &self,
+        origin: &Vec2,
+        dir: &Vec2,
+        max_dist: f64,
+        radius: f64,
+    ) -> Option<WasmRayHit> {
+        let point = self.tree.raycast(*origin, *dir, max_dist, radius);
+        // only obstacles in front of the point can be hit first
+        let reach = point.map_or(max_dist, |(_, dist)| dist);
+        match self.obstacles.raycast(*origin, *dir, reach) {
+            Some((obstacle, dist)) => Some(WasmRayHit {
+                item: obstacle.center,
+                radius: obstacle.radius,
+                dist,
+            }),
+            None => point.map(|(item, dist)| WasmRayHit { item, radius, dist }),
+        }
+    }
+    pub fn stats(&self) -> QuadTreeStats {
+        self.tree.stats()
+    }
+    /**
+    removes the points and the obstacles
+     */
     pub fn clear(&mut self) {
-        self.tree.reset(Vec2::zero(), self.tree.border_dims);
+        self.tree.reset(Vec2::zero(), self.initial_dims);
+        self.obstacles.reset(Vec2::zero(), self.initial_dims);
     }
     pub fn node_len(&self, node_idx: usize) -> usize {
         self.tree.node_len(node_idx)
@@ -118,3 +242,45 @@ fn test_rolling_avg() {
     avg.push(4.0);
     assert_eq!(avg.query(), 3.0);
 }
+
+#[test]
+fn test_wasm_quadtree_raycast() {
+    let mut tree = WasmQuadTree::new(&Vec2::from(100.0));
+    tree.push(&Vec2::new(80.0, 50.0));
+    let origin = Vec2::new(0.0, 50.0);
+    let dir = Vec2::new(1.0, 0.0);
+    let hit = tree.raycast(&origin, &dir, 100.0, 2.0).unwrap();
+    assert_eq!(
+        (hit.item, hit.radius, hit.dist),
+        (Vec2::new(80.0, 50.0), 2.0, 78.0)
+    );
+
+    // an obstacle in front of the point blocks it
+    assert!(tree.add_obstacle(&Vec2::new(40.0, 55.0), 10.0));
+    assert!(!tree.add_obstacle(&Vec2::new(140.0, 50.0), 10.0));
+    assert_eq!(tree.num_obstacles(), 1);
+    let hit = tree.raycast(&origin, &dir, 100.0, 2.0).unwrap();
+    assert_eq!((hit.item, hit.radius), (Vec2::new(40.0, 55.0), 10.0));
+    assert!((hit.dist - (40.0 - 75f64.sqrt())).abs() < 1e-9);
+    // and one behind it does not
+    let hit = tree.raycast(&Vec2::new(90.0, 50.0), &-dir, 100.0, 2.0);
+    assert_eq!(hit.unwrap().item, Vec2::new(80.0, 50.0));
+
+    // obstacles of different sizes are each hit at their
+    // own radius, the points all at the one passed in
+    assert!(tree.add_obstacle(&Vec2::new(60.0, 30.0), 1.0));
+    assert!(tree.add_obstacle(&Vec2::new(70.0, 30.0), 4.0));
+    let origin = Vec2::new(0.0, 33.0);
+    let hit = tree.raycast(&origin, &dir, 100.0, 2.0).unwrap();
+    assert_eq!((hit.item, hit.radius), (Vec2::new(70.0, 30.0), 4.0));
+    assert!((hit.dist - (70.0 - 7f64.sqrt())).abs() < 1e-9);
+    tree.push(&Vec2::new(50.0, 30.0));
+    let hit = tree.raycast(&origin, &dir, 100.0, 2.0).unwrap();
+    assert_eq!(hit.item, Vec2::new(70.0, 30.0));
+    let hit = tree.raycast(&origin, &dir, 100.0, 4.0).unwrap();
+    assert_eq!((hit.item, hit.radius), (Vec2::new(50.0, 30.0), 4.0));
+
+    tree.clear();
+    assert_eq!(tree.num_obstacles(), 0);
+    assert!(tree.raycast(&origin, &dir, 100.0, 2.0).is_none());
+}
@@ -0,0 +1,457 @@
+use crate::{
+    quadtree::{
+        dist_squared_point_rect, ray_circle_dist, ray_rect_range, DEFAULT_MAX_DEPTH,
+        DEFAULT_MAX_POINTS,
+    },
+    vec2::{Position, Vec2},
+};
+
+/**
+something with a position and a bounding circle
+ */
+pub trait Bounded: Position {
+    fn radius(&self) -> f64;
+}
+
+/**
+circle that is its own bounds, for obstacles
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Circle {
+    pub center: Vec2,
+    pub radius: f64,
+}
+impl Position for Circle {
+    fn pos(&self) -> Vec2 {
+        self.center
+    }
+}
+impl Bounded for Circle {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LooseQuadTreeNode<T>
+where
+    T: Bounded + Copy,
+{
+    // tl, tr, bl, br, 0 if missing
+    children: [usize; 4],
+    rect_tl: Vec2,
+    rect_dims: Vec2,
+    // largest radius of any item in this node or below it,
+    // the node's loose bounds are its rect grown by this
+    max_radius: f64,
+    items: Vec<T>,
+}
+impl<T> LooseQuadTreeNode<T>
+where
+    T: Bounded + Copy,
+{
+    fn new(rect_top_left: Vec2, rect_dims: Vec2) -> Self {
+        Self {
+            children: [0; 4],
+            rect_tl: rect_top_left,
+            rect_dims,
+            max_radius: 0.0,
+            items: Vec::new(),
+        }
+    }
+}
+
+/**
+quadtree of circles, items are placed by their center
+like in QuadTree, and each node's bounds are loosened by
+the radii stored below it so overlap queries stay exact
+ */
+pub struct LooseQuadTree<T>
+where
+    T: Bounded + Copy,
+{
+    // root is at nodes[0]
+    pub border_top_left: Vec2,
+    pub border_dims: Vec2,
+    nodes: Vec<LooseQuadTreeNode<T>>,
+    max_points: usize,
+    max_depth: usize,
+    num_items: usize,
+}
+impl<T> LooseQuadTree<T>
+where
+    T: Bounded + Copy,
+{
+    pub fn new(border_top_left: Vec2, border_dims: Vec2) -> Self {
+        Self::with_params(
+            border_top_left,
+            border_dims,
+            DEFAULT_MAX_POINTS,
+            DEFAULT_MAX_DEPTH,
+        )
+    }
+    pub fn with_params(
+        border_top_left: Vec2,
+        border_dims: Vec2,
+        max_points: usize,
+        max_depth: usize,
+    ) -> Self {
+        Self {
+            border_top_left,
+            border_dims,
+            nodes: vec![LooseQuadTreeNode::new(border_top_left, border_dims)],
+            max_points: max_points.max(1),
+            max_depth,
+            num_items: 0,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.num_items
+    }
+    pub fn is_empty(&self) -> bool {
+        self.num_items == 0
+    }
+    pub fn reset(&mut self, border_top_left: Vec2, border_dims: Vec2) {
+        self.nodes.clear();
+        self.nodes
+            .push(LooseQuadTreeNode::new(border_top_left, border_dims));
+        self.border_top_left = border_top_left;
+        self.border_dims = border_dims;
+        self.num_items = 0;
+    }
+    /**
+    returns false if the item's center is outside the borders,
+    its radius may extend past them
+     */
+    pub fn push(&mut self, item: &T) -> bool {
+        let pos = item.pos();
+        if !(pos.x >= self.border_top_left.x
+            && pos.x <= self.border_top_left.x + self.border_dims.x
+            && pos.y >= self.border_top_left.y
+            && pos.y <= self.border_top_left.y + self.border_dims.y)
+        {
+            return false;
+        }
+
+        let radius = item.radius().max(0.0);
+        let mut cur_idx = 0;
+        let mut depth = 0;
+        loop {
+            let node = &mut self.nodes[cur_idx];
+            node.max_radius = node.max_radius.max(radius);
+
+            // large items stay high up, so they only
+            // loosen the bounds of a few big nodes
+            let half_dims = node.rect_dims / 2.0;
+            let fits_child = radius <= f64::min(half_dims.x, half_dims.y) / 2.0;
+            if node.items.len() < self.max_points || depth >= self.max_depth || !fits_child {
+                node.items.push(*item);
+                self.num_items += 1;
+                return true;
+            }
+
+            let mid = node.rect_tl + half_dims;
+            let left = pos.x <= mid.x;
+            let top = pos.y <= mid.y;
+            let quadrant = match (top, left) {
+                (true, true) => 0,
+                (true, false) => 1,
+                (false, true) => 2,
+                (false, false) => 3,
+            };
+            if node.children[quadrant] == 0 {
+                let child_tl = Vec2::new(
+                    if left { node.rect_tl.x } else { mid.x },
+                    if top { node.rect_tl.y } else { mid.y },
+                );
+                let child_idx = self.nodes.len();
+                self.nodes[cur_idx].children[quadrant] = child_idx;
+                self.nodes.push(LooseQuadTreeNode::new(child_tl, half_dims));
+            }
+            cur_idx = self.nodes[cur_idx].children[quadrant];
+            depth += 1;
+        }
+    }
+    /**
+    calls f on every item whose circle overlaps the given one,
+    touching circles do not overlap
+     */
+    pub fn overlapping_circle_with<F>(&self, center: Vec2, radius: f64, mut f: F)
+    where
+        F: FnMut(&T),
+    {
+        if self.num_items == 0 {
+            return;
+        }
+        self.overlapping_node(0, center, radius, &mut f);
+    }
+    fn overlapping_node<F>(&self, cur: usize, center: Vec2, radius: f64, f: &mut F)
+    where
+        F: FnMut(&T),
+    {
+        let node = &self.nodes[cur];
+        let reach = radius + node.max_radius;
+        if dist_squared_point_rect(center, node.rect_tl, node.rect_dims) >= reach * reach {
+            return;
+        }
+        for item in &node.items {
+            let sum = radius + item.radius();
+            if item.pos().distance_squared(&center) < sum * sum {
+                f(item);
+            }
+        }
+        for child_idx in node.children {
+            if child_idx != 0 {
+                self.overlapping_node(child_idx, center, radius, f);
+            }
+        }
+    }
+    /**
+    every item overlapping item, including
+    item itself if it is in the tree
+     */
+    pub fn overlapping(&self, item: &T) -> Vec<T> {
+        let mut ret = Vec::new();
+        self.overlapping_circle_with(item.pos(), item.radius(), |other| ret.push(*other));
+        ret
+    }
+    /**
+    first item whose circle is hit by a ray, with the distance
+    along the ray to it. dir does not need to be normalized
+     */
+    pub fn raycast(&self, origin: Vec2, dir: Vec2, max_dist: f64) -> Option<(T, f64)> {
+        let len = dir.length();
+        if self.num_items == 0 || len == 0.0 || !len.is_finite() {
+            return None;
+        }
+        let dir = dir / len;
+        let mut best = None;
+        let mut best_dist = max_dist;
+        self.raycast_node(0, origin, dir, &mut best, &mut best_dist);
+        best.map(|item| (item, best_dist))
+    }
+    fn raycast_node(
+        &self,
+        cur: usize,
+        origin: Vec2,
+        dir: Vec2,
+        best: &mut Option<T>,
+        best_dist: &mut f64,
+    ) {
+        let node = &self.nodes[cur];
+        for item in &node.items {
+            if let Some(dist) = ray_circle_dist(origin, dir, item.pos(), item.radius()) {
+                if dist <= *best_dist {
+                    *best = Some(*item);
+                    *best_dist = dist;
+                }
+            }
+        }
+
+        // visit the children the ray crosses from front to back,
+        // through their loose bounds
+        let mut crossed = [(0, 0.0); 4];
+        let mut num_crossed = 0;
+        for child_idx in node.children {
+            if child_idx == 0 {
+                continue;
+            }
+            let child = &self.nodes[child_idx];
+            if let Some((enter, exit)) = ray_rect_range(
+                origin,
+                dir,
+                child.rect_tl - child.max_radius,
+                child.rect_dims + child.max_radius * 2.0,
+            ) {
+                if exit >= 0.0 && enter <= *best_dist {
+                    crossed[num_crossed] = (child_idx, enter.max(0.0));
+                    num_crossed += 1;
+                }
+            }
+        }
+        let crossed = &mut crossed[..num_crossed];
+        crossed.sort_by(|a, b| a.1.total_cmp(&b.1));
+        for (child_idx, enter) in crossed {
+            // a closer hit was found in an earlier child
+            if *enter > *best_dist {
+                break;
+            }
+            self.raycast_node(*child_idx, origin, dir, best, best_dist);
+        }
+    }
+    #[cfg(test)]
+    fn overlapping_brute_force(&self, item: &T) -> Vec<T> {
+        let mut ret = Vec::new();
+        for node in &self.nodes {
+            for other in &node.items {
+                if other.pos().distance(&item.pos()) < other.radius() + item.radius() {
+                    ret.push(*other);
+                }
+            }
+        }
+        ret
+    }
+}
+
+#[test]
+fn test_loose_quadtree_push() {
+    let mut tree = LooseQuadTree::new(Vec2::zero(), Vec2::from(10.0));
+    let circle = |x: f64, y: f64, radius: f64| Circle {
+        center: Vec2::new(x, y),
+        radius,
+    };
+    assert!(tree.push(&circle(0.0, 0.0, 100.0)));
+    assert!(tree.push(&circle(10.0, 10.0, 0.1)));
+    assert!(!tree.push(&circle(-0.1, 5.0, 1.0)));
+    assert_eq!(tree.len(), 2);
+
+    // a big circle stays in the root even when it is full
+    for _ in 0..4 {
+        tree.push(&circle(1.0, 1.0, 0.5));
+    }
+    tree.push(&circle(1.0, 1.0, 3.0));
+    assert_eq!(tree.nodes[0].items.len(), 5);
+    assert_eq!(tree.nodes[0].max_radius, 100.0);
+
+    tree.reset(Vec2::zero(), Vec2::from(20.0));
+    assert_eq!(tree.len(), 0);
+    assert!(tree.is_empty());
+    assert!(tree.overlapping(&circle(1.0, 1.0, 100.0)).is_empty());
+}
+
+#[test]
+fn test_loose_quadtree_overlapping() {
+    let mut tree = LooseQuadTree::new(Vec2::zero(), Vec2::from(100.0));
+    for i in 0..=20 {
+        for j in 0..=20 {
+            // radii between 0 and 12
+            let radius = ((i * 7 + j * 13) % 25) as f64 / 2.0;
+            tree.push(&Circle {
+                center: Vec2::new(i as f64 * 5.0, j as f64 * 5.0),
+                radius,
+            });
+        }
+    }
+    let queries = [
+        Circle {
+            center: Vec2::new(50.0, 50.0),
+            radius: 0.0,
+        },
+        Circle {
+            center: Vec2::new(52.5, 47.5),
+            radius: 2.5,
+        },
+        Circle {
+            center: Vec2::new(-10.0, 30.0),
+            radius: 4.0,
+        },
+        Circle {
+            center: Vec2::new(99.0, 1.0),
+            radius: 20.0,
+        },
+        Circle {
+            center: Vec2::new(50.0, 50.0),
+            radius: 200.0,
+        },
+    ];
+    let by_pos = |a: &Circle, b: &Circle| {
+        a.center
+            .x
+            .total_cmp(&b.center.x)
+            .then(a.center.y.total_cmp(&b.center.y))
+    };
+    for query in queries {
+        let mut got = tree.overlapping(&query);
+        let mut expected = tree.overlapping_brute_force(&query);
+        got.sort_by(by_pos);
+        expected.sort_by(by_pos);
+        assert_eq!(got, expected);
+    }
+
+    // touching circles do not overlap
+    let mut tree = LooseQuadTree::new(Vec2::zero(), Vec2::from(10.0));
+    tree.push(&Circle {
+        center: Vec2::new(2.0, 5.0),
+        radius: 1.0,
+    });
+    let touching = Circle {
+        center: Vec2::new(4.0, 5.0),
+        radius: 1.0,
+    };
+    assert!(tree.overlapping(&touching).is_empty());
+}
+
+#[test]
+fn test_loose_quadtree_raycast() {
+    let mut tree = LooseQuadTree::new(Vec2::zero(), Vec2::from(100.0));
+    for i in 0..=20 {
+        for j in 0..=20 {
+            // radii between 0 and 6, some circles are empty
+            if (i + j) % 3 == 0 {
+                continue;
+            }
+            let radius = ((i * 7 + j * 13) % 25) as f64 / 4.0;
+            tree.push(&Circle {
+                center: Vec2::new(i as f64 * 5.0, j as f64 * 5.0),
+                radius,
+            });
+        }
+    }
+    // a big obstacle stays near the root
+    tree.push(&Circle {
+        center: Vec2::new(70.0, 30.0),
+        radius: 15.0,
+    });
+
+    // the first hit is the closest circle the ray enters
+    let brute_force = |origin: Vec2, dir: Vec2, max_dist: f64| {
+        let dir = dir.normalize();
+        let mut best: Option<f64> = None;
+        for node in &tree.nodes {
+            for item in &node.items {
+                if let Some(dist) = ray_circle_dist(origin, dir, item.center, item.radius) {
+                    if dist <= max_dist && best.map_or(true, |best| dist < best) {
+                        best = Some(dist);
+                    }
+                }
+            }
+        }
+        best
+    };
+    for (x, y) in [(-10.0, 2.0), (50.0, 110.0), (101.0, 28.0), (12.0, 13.0)] {
+        let origin = Vec2::new(x, y);
+        for k in 0..16 {
+            let angle = k as f64 * std::f64::consts::PI / 8.0;
+            let dir = Vec2::new(angle.cos(), angle.sin());
+            let hit = tree.raycast(origin, dir, 150.0);
+            let expected = brute_force(origin, dir, 150.0);
+            assert_eq!(hit.map(|(_, dist)| dist), expected);
+            if let Some((item, dist)) = hit {
+                let point = origin + dir * dist;
+                assert!((point.distance(&item.center) - item.radius).abs() < 1e-9 || dist == 0.0);
+            }
+        }
+    }
+
+    // an obstacle is hit on its edge, not at its center
+    let mut tree = LooseQuadTree::new(Vec2::zero(), Vec2::from(100.0));
+    for i in 0..20 {
+        tree.push(&Circle {
+            center: Vec2::new(i as f64 * 5.0, 90.0),
+            radius: 1.0,
+        });
+    }
+    tree.push(&Circle {
+        center: Vec2::new(70.0, 30.0),
+        radius: 15.0,
+    });
+    let (item, dist) = tree
+        .raycast(Vec2::new(70.0, -50.0), Vec2::new(0.0, 3.0), 100.0)
+        .unwrap();
+    assert_eq!(item.radius, 15.0);
+    assert!((dist - 65.0).abs() < 1e-9);
+    assert!(tree
+        .raycast(Vec2::new(70.0, -50.0), Vec2::new(0.0, 1.0), 60.0)
+        .is_none());
+    assert!(tree.raycast(Vec2::zero(), Vec2::zero(), 10.0).is_none());
+}
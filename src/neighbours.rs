@@ -0,0 +1,470 @@
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::vec2::Vec2;
+
+/**
+neighbours are summed this many at a time,
+4 f64 lanes fill an avx register
+ */
+#[cfg(feature = "simd")]
+pub const LANES: usize = 4;
+
+#[cfg(all(
+    feature = "simd",
+    target_arch = "wasm32",
+    not(target_feature = "simd128")
+))]
+compile_error!("the simd feature needs RUSTFLAGS=\"-C target-feature=+simd128\" on wasm32");
+
+/**
+how hard a neighbour pushes in separation depending on how
+close it is. the inverse modes push as hard as linear at the
+edge of the protected range and harder closer in
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeparationFalloff {
+    // proportional to the distance, so the
+    // furthest neighbours push the hardest
+    Linear,
+    Inverse,
+    InverseSquare,
+    // strongest when touching, fading to nothing at the edge
+    Smooth,
+}
+impl SeparationFalloff {
+    /**
+    factor on the displacement from a neighbour at dist_sq,
+    which is more than 0 and at most protect_sq
+     */
+    #[inline(always)]
+    pub fn weight(&self, dist_sq: f64, protect_sq: f64) -> f64 {
+        match self {
+            SeparationFalloff::Linear => 1.0,
+            SeparationFalloff::Inverse => protect_sq / dist_sq,
+            SeparationFalloff::InverseSquare => {
+                let q = protect_sq / dist_sq;
+                q * q.sqrt()
+            }
+            SeparationFalloff::Smooth => {
+                let s = 1.0 - dist_sq / protect_sq;
+                (protect_sq / dist_sq).sqrt() * s * s
+            }
+        }
+    }
+}
+
+/**
+sums over the neighbours of a boid that the
+flocking rules are computed from
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NeighbourSums {
+    // sum of center - pos over neighbours in the protected
+    // range, weighted by the separation falloff
+    pub close: Vec2,
+    // sums over neighbours in the visible range,
+    // including the boid at the center
+    pub vel: Vec2,
+    pub pos: Vec2,
+    pub visible: usize,
+}
+
+/**
+positions and velocities of the boids around one boid,
+gathered into contiguous buffers for the accumulation
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Neighbours {
+    xs: Vec<f64>,
+    ys: Vec<f64>,
+    vxs: Vec<f64>,
+    vys: Vec<f64>,
+    // traversal stack of the spatial index query, kept
+    // with the buffers so that every thread has its own
+    pub(crate) stack: Vec<usize>,
+}
+impl Neighbours {
+    pub fn len(&self) -> usize {
+        self.xs.len()
+    }
+    pub fn clear(&mut self) {
+        self.xs.clear();
+        self.ys.clear();
+        self.vxs.clear();
+        self.vys.clear();
+    }
+    pub fn push(&mut self, pos: Vec2, vel: Vec2) {
+        self.xs.push(pos.x);
+        self.ys.push(pos.y);
+        self.vxs.push(vel.x);
+        self.vys.push(vel.y);
+    }
+    /**
+    sums every neighbour within the squared
+    ranges of center in a single pass
+     */
+    pub fn accumulate(
+        &mut self,
+        center: Vec2,
+        protect_sq: f64,
+        visible_sq: f64,
+        falloff: SeparationFalloff,
+    ) -> NeighbourSums {
+        // one copy of the loop per falloff, so linear
+        // does not pay for the others
+        #[cfg(not(feature = "simd"))]
+        let sums = match falloff {
+            SeparationFalloff::Linear => {
+                self.accumulate_scalar(center, protect_sq, visible_sq, |_| 1.0)
+            }
+            SeparationFalloff::Inverse => {
+                self.accumulate_scalar(center, protect_sq, visible_sq, |dist_sq| {
+                    SeparationFalloff::Inverse.weight(dist_sq, protect_sq)
+                })
+            }
+            SeparationFalloff::InverseSquare => {
+                self.accumulate_scalar(center, protect_sq, visible_sq, |dist_sq| {
+                    SeparationFalloff::InverseSquare.weight(dist_sq, protect_sq)
+                })
+            }
+            SeparationFalloff::Smooth => {
+                self.accumulate_scalar(center, protect_sq, visible_sq, |dist_sq| {
+                    SeparationFalloff::Smooth.weight(dist_sq, protect_sq)
+                })
+            }
+        };
+        #[cfg(feature = "simd")]
+        let sums = self.accumulate_padded(center, protect_sq, visible_sq, falloff);
+        sums
+    }
+    /**
+    one neighbour at a time, without the simd feature.
+    in wasm without simd128 this beats the lane version
+     */
+    #[cfg(not(feature = "simd"))]
+    #[inline(always)]
+    fn accumulate_scalar<W>(
+        &self,
+        center: Vec2,
+        protect_sq: f64,
+        visible_sq: f64,
+        weight: W,
+    ) -> NeighbourSums
+    where
+        W: Fn(f64) -> f64,
+    {
+        let mut sums = NeighbourSums {
+            close: Vec2::zero(),
+            vel: Vec2::zero(),
+            pos: Vec2::zero(),
+            visible: 0,
+        };
+        for i in 0..self.len() {
+            let pos = Vec2::new(self.xs[i], self.ys[i]);
+            let diff = center - pos;
+            let dist_sq = diff.length_squared();
+            // the boid itself and boids on top of
+            // it have no direction to push in
+            if dist_sq <= protect_sq && dist_sq > 0.0 {
+                sums.close += diff * weight(dist_sq);
+            }
+            if dist_sq <= visible_sq {
+                sums.vel += Vec2::new(self.vxs[i], self.vys[i]);
+                sums.pos += pos;
+                sums.visible += 1;
+            }
+        }
+        sums
+    }
+    /**
+    pads the buffers to whole lanes for
+    the vectorized versions
+     */
+    #[cfg(feature = "simd")]
+    fn accumulate_padded(
+        &mut self,
+        center: Vec2,
+        protect_sq: f64,
+        visible_sq: f64,
+        falloff: SeparationFalloff,
+    ) -> NeighbourSums {
+        // pad to whole lanes with boids infinitely far
+        // away, they fail every range check
+        let len = self.len();
+        let padded = len.next_multiple_of(LANES);
+        self.xs.resize(padded, f64::INFINITY);
+        self.ys.resize(padded, f64::INFINITY);
+        self.vxs.resize(padded, 0.0);
+        self.vys.resize(padded, 0.0);
+
+        #[cfg(target_arch = "wasm32")]
+        let sums = match falloff {
+            SeparationFalloff::Linear => {
+                simd128::accumulate(self, center, protect_sq, visible_sq, simd128::linear)
+            }
+            SeparationFalloff::Inverse => {
+                simd128::accumulate(self, center, protect_sq, visible_sq, |dist_sq| {
+                    simd128::weight(SeparationFalloff::Inverse, dist_sq, protect_sq)
+                })
+            }
+            SeparationFalloff::InverseSquare => {
+                simd128::accumulate(self, center, protect_sq, visible_sq, |dist_sq| {
+                    simd128::weight(SeparationFalloff::InverseSquare, dist_sq, protect_sq)
+                })
+            }
+            SeparationFalloff::Smooth => {
+                simd128::accumulate(self, center, protect_sq, visible_sq, |dist_sq| {
+                    simd128::weight(SeparationFalloff::Smooth, dist_sq, protect_sq)
+                })
+            }
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let sums = match falloff {
+            SeparationFalloff::Linear => {
+                self.accumulate_lanes(center, protect_sq, visible_sq, |_| 1.0)
+            }
+            SeparationFalloff::Inverse => {
+                self.accumulate_lanes(center, protect_sq, visible_sq, |dist_sq| {
+                    SeparationFalloff::Inverse.weight(dist_sq, protect_sq)
+                })
+            }
+            SeparationFalloff::InverseSquare => {
+                self.accumulate_lanes(center, protect_sq, visible_sq, |dist_sq| {
+                    SeparationFalloff::InverseSquare.weight(dist_sq, protect_sq)
+                })
+            }
+            SeparationFalloff::Smooth => {
+                self.accumulate_lanes(center, protect_sq, visible_sq, |dist_sq| {
+                    SeparationFalloff::Smooth.weight(dist_sq, protect_sq)
+                })
+            }
+        };
+
+        self.xs.truncate(len);
+        self.ys.truncate(len);
+        self.vxs.truncate(len);
+        self.vys.truncate(len);
+        sums
+    }
+    /**
+    portable version, one accumulator per lane and no
+    branches so the compiler can vectorize the lane loop
+     */
+    #[cfg(all(feature = "simd", not(target_arch = "wasm32")))]
+    #[inline(always)]
+    fn accumulate_lanes<W>(
+        &self,
+        center: Vec2,
+        protect_sq: f64,
+        visible_sq: f64,
+        weight: W,
+    ) -> NeighbourSums
+    where
+        W: Fn(f64) -> f64,
+    {
+        let mut close_x = [0.0; LANES];
+        let mut close_y = [0.0; LANES];
+        let mut vel_x = [0.0; LANES];
+        let mut vel_y = [0.0; LANES];
+        let mut pos_x = [0.0; LANES];
+        let mut pos_y = [0.0; LANES];
+        let mut visible = [0.0; LANES];
+
+        let chunks = self
+            .xs
+            .chunks_exact(LANES)
+            .zip(self.ys.chunks_exact(LANES))
+            .zip(self.vxs.chunks_exact(LANES))
+            .zip(self.vys.chunks_exact(LANES));
+        for (((xs, ys), vxs), vys) in chunks {
+            for lane in 0..LANES {
+                let dx = center.x - xs[lane];
+                let dy = center.y - ys[lane];
+                let dist_sq = dx * dx + dy * dy;
+                // the boid itself and boids on top of
+                // it have no direction to push in
+                let protected = dist_sq <= protect_sq && dist_sq > 0.0;
+                let seen = dist_sq <= visible_sq;
+                let w = weight(dist_sq);
+                close_x[lane] += if protected { dx * w } else { 0.0 };
+                close_y[lane] += if protected { dy * w } else { 0.0 };
+                vel_x[lane] += if seen { vxs[lane] } else { 0.0 };
+                vel_y[lane] += if seen { vys[lane] } else { 0.0 };
+                pos_x[lane] += if seen { xs[lane] } else { 0.0 };
+                pos_y[lane] += if seen { ys[lane] } else { 0.0 };
+                visible[lane] += if seen { 1.0 } else { 0.0 };
+            }
+        }
+
+        let sum = |lanes: [f64; LANES]| lanes.iter().sum::<f64>();
+        NeighbourSums {
+            close: Vec2::new(sum(close_x), sum(close_y)),
+            vel: Vec2::new(sum(vel_x), sum(vel_y)),
+            pos: Vec2::new(sum(pos_x), sum(pos_y)),
+            visible: sum(visible) as usize,
+        }
+    }
+}
+
+/**
+explicit wasm simd128 version, two f64 lanes per op
+ */
+#[cfg(all(feature = "simd", target_arch = "wasm32"))]
+mod simd128 {
+    use core::arch::wasm32::*;
+
+    use super::{NeighbourSums, Neighbours, SeparationFalloff};
+    use crate::vec2::Vec2;
+
+    fn sum(v: v128) -> f64 {
+        f64x2_extract_lane::<0>(v) + f64x2_extract_lane::<1>(v)
+    }
+
+    #[inline(always)]
+    pub fn linear(_dist_sq: v128) -> v128 {
+        f64x2_splat(1.0)
+    }
+
+    /**
+    SeparationFalloff::weight on both lanes
+     */
+    #[inline(always)]
+    pub fn weight(falloff: SeparationFalloff, dist_sq: v128, protect_sq: f64) -> v128 {
+        let protect_sq = f64x2_splat(protect_sq);
+        let q = f64x2_div(protect_sq, dist_sq);
+        match falloff {
+            SeparationFalloff::Linear => linear(dist_sq),
+            SeparationFalloff::Inverse => q,
+            SeparationFalloff::InverseSquare => f64x2_mul(q, f64x2_sqrt(q)),
+            SeparationFalloff::Smooth => {
+                let s = f64x2_sub(f64x2_splat(1.0), f64x2_div(dist_sq, protect_sq));
+                f64x2_mul(f64x2_sqrt(q), f64x2_mul(s, s))
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub fn accumulate<W>(
+        neighbours: &Neighbours,
+        center: Vec2,
+        protect_sq: f64,
+        visible_sq: f64,
+        weight: W,
+    ) -> NeighbourSums
+    where
+        W: Fn(v128) -> v128,
+    {
+        let center_x = f64x2_splat(center.x);
+        let center_y = f64x2_splat(center.y);
+        let protect_sq = f64x2_splat(protect_sq);
+        let visible_sq = f64x2_splat(visible_sq);
+        let one = f64x2_splat(1.0);
+        let zero = f64x2_splat(0.0);
+
+        let (mut close_x, mut close_y) = (zero, zero);
+        let (mut vel_x, mut vel_y) = (zero, zero);
+        let (mut pos_x, mut pos_y) = (zero, zero);
+        let mut visible = zero;
+
+        let chunks = neighbours
+            .xs
+            .chunks_exact(2)
+            .zip(neighbours.ys.chunks_exact(2))
+            .zip(neighbours.vxs.chunks_exact(2))
+            .zip(neighbours.vys.chunks_exact(2));
+        for (((xs, ys), vxs), vys) in chunks {
+            let x = f64x2(xs[0], xs[1]);
+            let y = f64x2(ys[0], ys[1]);
+            let dx = f64x2_sub(center_x, x);
+            let dy = f64x2_sub(center_y, y);
+            let dist_sq = f64x2_add(f64x2_mul(dx, dx), f64x2_mul(dy, dy));
+            // all ones where in range, and-ing zeroes the rest.
+            // the boid itself and boids on top of it have no
+            // direction to push in
+            let protected = v128_and(f64x2_le(dist_sq, protect_sq), f64x2_gt(dist_sq, zero));
+            let seen = f64x2_le(dist_sq, visible_sq);
+            let w = weight(dist_sq);
+            close_x = f64x2_add(close_x, v128_and(f64x2_mul(dx, w), protected));
+            close_y = f64x2_add(close_y, v128_and(f64x2_mul(dy, w), protected));
+            vel_x = f64x2_add(vel_x, v128_and(f64x2(vxs[0], vxs[1]), seen));
+            vel_y = f64x2_add(vel_y, v128_and(f64x2(vys[0], vys[1]), seen));
+            pos_x = f64x2_add(pos_x, v128_and(x, seen));
+            pos_y = f64x2_add(pos_y, v128_and(y, seen));
+            visible = f64x2_add(visible, v128_and(one, seen));
+        }
+
+        NeighbourSums {
+            close: Vec2::new(sum(close_x), sum(close_y)),
+            vel: Vec2::new(sum(vel_x), sum(vel_y)),
+            pos: Vec2::new(sum(pos_x), sum(pos_y)),
+            visible: sum(visible) as usize,
+        }
+    }
+}
+
+#[test]
+fn test_neighbours_accumulate() {
+    let mut neighbours = Neighbours::default();
+    let center = Vec2::new(10.0, 10.0);
+    // not a whole number of lanes
+    neighbours.push(center, Vec2::new(1.0, 0.0));
+    neighbours.push(Vec2::new(12.0, 10.0), Vec2::new(2.0, 1.0));
+    neighbours.push(Vec2::new(10.0, 6.0), Vec2::new(-1.0, 3.0));
+    neighbours.push(Vec2::new(13.0, 14.0), Vec2::new(0.0, 5.0));
+    neighbours.push(Vec2::new(30.0, 30.0), Vec2::new(7.0, 7.0));
+
+    // protected within 3, visible within 5
+    let sums = neighbours.accumulate(center, 9.0, 25.0, SeparationFalloff::Linear);
+    assert_eq!(sums.close, Vec2::new(-2.0, 0.0));
+    assert_eq!(sums.vel, Vec2::new(2.0, 9.0));
+    assert_eq!(sums.pos, Vec2::new(45.0, 40.0));
+    assert_eq!(sums.visible, 4);
+
+    // padding is removed again
+    assert_eq!(neighbours.len(), 5);
+    neighbours.clear();
+    assert_eq!(neighbours.len(), 0);
+    let sums = neighbours.accumulate(center, 9.0, 25.0, SeparationFalloff::Linear);
+    assert_eq!(sums.visible, 0);
+    assert_eq!(sums.close, Vec2::zero());
+}
+
+#[test]
+fn test_separation_falloff() {
+    let modes = [
+        SeparationFalloff::Linear,
+        SeparationFalloff::Inverse,
+        SeparationFalloff::InverseSquare,
+        SeparationFalloff::Smooth,
+    ];
+    // protected within 4, one neighbour at 1 and one at 4
+    let protect_sq = 16.0;
+    let push = |mode: SeparationFalloff, dist: f64| dist * mode.weight(dist * dist, protect_sq);
+    assert_eq!(push(SeparationFalloff::Linear, 1.0), 1.0);
+    assert_eq!(push(SeparationFalloff::Inverse, 1.0), 16.0);
+    assert_eq!(push(SeparationFalloff::InverseSquare, 1.0), 64.0);
+    assert_eq!(
+        push(SeparationFalloff::Smooth, 1.0),
+        4.0 * (15.0 / 16.0f64).powi(2)
+    );
+    // the inverse modes match linear at the edge, smooth fades out
+    for mode in &modes[..3] {
+        assert_eq!(push(*mode, 4.0), 4.0);
+    }
+    assert_eq!(push(SeparationFalloff::Smooth, 4.0), 0.0);
+
+    let mut neighbours = Neighbours::default();
+    let center = Vec2::new(10.0, 10.0);
+    neighbours.push(center, Vec2::zero());
+    // on top of the center, pushes nowhere
+    neighbours.push(center, Vec2::zero());
+    neighbours.push(Vec2::new(11.0, 10.0), Vec2::zero());
+    neighbours.push(Vec2::new(10.0, 7.0), Vec2::zero());
+    for mode in modes {
+        let sums = neighbours.accumulate(center, protect_sq, protect_sq, mode);
+        let expected = Vec2::new(-push(mode, 1.0), push(mode, 3.0));
+        assert!((sums.close - expected).length() < 1e-12);
+        assert_eq!(sums.visible, 4);
+    }
+}
@@ -0,0 +1,410 @@
+use crate::{rng::Rng, vec2::Vec2, world::World};
+
+/**
+what a candidate is scored on, measured every tick
+after the warmup and averaged. higher is better
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fitness {
+    // length of the mean heading, 1 when every boid flies the same way
+    Polarization,
+    // pairs of boids closer than radius, fewer is better
+    Collisions { radius: f64 },
+    // groups of boids within visible_range of each
+    // other, closer to target is better
+    Clusters { target: usize },
+    // mean distance of the boids to target, closer is better
+    TargetDistance { target: Vec2 },
+}
+impl Fitness {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Fitness::Polarization => "polarization",
+            Fitness::Collisions { .. } => "collisions",
+            Fitness::Clusters { .. } => "clusters",
+            Fitness::TargetDistance { .. } => "target_distance",
+        }
+    }
+    /**
+    score of boids given as x, y, vx, vy, like World::boid_data
+     */
+    pub fn score(&self, boids: &[f64], visible_range: f64) -> f64 {
+        let n = boids.len() / 4;
+        if n == 0 {
+            return 0.0;
+        }
+        let pos = |i: usize| Vec2::new(boids[i * 4], boids[i * 4 + 1]);
+        let vel = |i: usize| Vec2::new(boids[i * 4 + 2], boids[i * 4 + 3]);
+        match *self {
+            Fitness::Polarization => {
+                let heading = (0..n)
+                    .map(vel)
+                    .filter(|&v| v != Vec2::zero())
+                    .fold(Vec2::zero(), |acc, v| acc + v.normalize());
+                heading.length() / n as f64
+            }
+            Fitness::Collisions { radius } => {
+                let mut pairs = 0;
+                for i in 0..n {
+                    for j in i + 1..n {
+                        if pos(i).distance_squared(&pos(j)) < radius * radius {
+                            pairs += 1;
+                        }
+                    }
+                }
+                -(pairs as f64)
+            }
+            Fitness::Clusters { target } => {
+                let count = count_clusters(n, visible_range, pos);
+                -(count.abs_diff(target) as f64)
+            }
+            Fitness::TargetDistance { target } => {
+                let total: f64 = (0..n).map(|i| pos(i).distance(&target)).sum();
+                -(total / n as f64)
+            }
+        }
+    }
+}
+
+/**
+connected components of the boids, linking
+every pair closer than range
+ */
+fn count_clusters(n: usize, range: f64, pos: impl Fn(usize) -> Vec2) -> usize {
+    // union find with path halving
+    let mut parent: Vec<usize> = (0..n).collect();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    let mut count = n;
+    for i in 0..n {
+        for j in i + 1..n {
+            if pos(i).distance_squared(&pos(j)) < range * range {
+                let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                if a != b {
+                    parent[a] = b;
+                    count -= 1;
+                }
+            }
+        }
+    }
+    count
+}
+
+/**
+rule weights being optimized
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Genome {
+    pub avoid_factor: f64,
+    pub align_factor: f64,
+    pub cohesion_factor: f64,
+}
+impl Genome {
+    fn genes(&self) -> [f64; 3] {
+        [self.avoid_factor, self.align_factor, self.cohesion_factor]
+    }
+    fn from_genes(genes: [f64; 3]) -> Self {
+        Self {
+            avoid_factor: genes[0],
+            align_factor: genes[1],
+            cohesion_factor: genes[2],
+        }
+    }
+}
+
+/**
+headless world every candidate is run in, everything
+but the genome is the same for all of them
+ */
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    pub dims: Vec2,
+    pub num_boids: usize,
+    pub visible_range: f64,
+    pub protect_range: f64,
+    pub margin: f64,
+    pub turn_factor: f64,
+    pub max_speed: f64,
+    pub min_speed: f64,
+    // ticks before measuring, so the random start does not count
+    pub warmup: usize,
+    // ticks measured after the warmup
+    pub ticks: usize,
+    // worlds per candidate, each from its own seed
+    pub runs: usize,
+}
+impl Default for Scenario {
+    /**
+    same ranges and speeds as the web app,
+    with fewer boids on a smaller canvas
+     */
+    fn default() -> Self {
+        Self {
+            dims: Vec2::new(800.0, 600.0),
+            num_boids: 200,
+            visible_range: 32.0,
+            protect_range: 8.0,
+            margin: 25.0,
+            turn_factor: 0.2,
+            max_speed: 4.0,
+            min_speed: 1.0,
+            warmup: 200,
+            ticks: 100,
+            runs: 2,
+        }
+    }
+}
+impl Scenario {
+    fn world(&self, genome: &Genome, seed: u64) -> World {
+        let mut world = World::new(
+            &self.dims,
+            self.visible_range,
+            self.protect_range,
+            genome.avoid_factor,
+            genome.align_factor,
+            genome.cohesion_factor,
+            self.margin,
+            self.turn_factor,
+            self.max_speed,
+            self.min_speed,
+        );
+        world.set_seed(seed as u32);
+        let mut rng = Rng::new(seed);
+        for _ in 0..self.num_boids {
+            let pos = Vec2::new(rng.range(0.0, self.dims.x), rng.range(0.0, self.dims.y));
+            let speed = rng.range(self.min_speed, self.max_speed);
+            let vel = Vec2::new(speed, 0.0).rotate(rng.range(0.0, std::f64::consts::TAU));
+            world.add_boid(&pos, &vel);
+        }
+        world
+    }
+    /**
+    mean score over every measured tick of every run. runs use
+    seeds 1 to runs, so all candidates start from the same flocks
+     */
+    pub fn evaluate(&self, genome: &Genome, fitness: Fitness) -> f64 {
+        let mut total = 0.0;
+        for seed in 1..=self.runs as u64 {
+            let mut world = self.world(genome, seed);
+            for _ in 0..self.warmup {
+                world.tick();
+            }
+            for _ in 0..self.ticks {
+                world.tick();
+                total += fitness.score(&world.boid_data(), self.visible_range);
+            }
+        }
+        total / (self.runs * self.ticks).max(1) as f64
+    }
+}
+
+/**
+genome and the score it got
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Candidate {
+    pub genome: Genome,
+    pub score: f64,
+}
+impl Candidate {
+    /**
+    the genome and score as a json object
+     */
+    pub fn to_json(&self, fitness: Fitness) -> String {
+        format!(
+            "{{\n  \"fitness\": \"{}\",\n  \"score\": {},\n  \"avoid_factor\": {},\n  \"align_factor\": {},\n  \"cohesion_factor\": {}\n}}",
+            fitness.name(),
+            self.score,
+            self.genome.avoid_factor,
+            self.genome.align_factor,
+            self.genome.cohesion_factor,
+        )
+    }
+}
+
+/**
+genetic algorithm over the rule weights, with tournament
+selection, blend crossover and gaussian mutation
+ */
+#[derive(Clone, Debug)]
+pub struct Optimizer {
+    pub scenario: Scenario,
+    pub fitness: Fitness,
+    // candidates per generation
+    pub population: usize,
+    pub generations: usize,
+    // best candidates copied unchanged into the next generation
+    pub elites: usize,
+    // candidates each parent is picked from, the best one wins
+    pub tournament: usize,
+    // standard deviation of the mutation, as a fraction of each gene's range
+    pub mutation: f64,
+    // every gene stays within these
+    pub min: Genome,
+    pub max: Genome,
+    pub seed: u64,
+}
+impl Optimizer {
+    /**
+    gene ranges match the sliders of the web app
+     */
+    pub fn new(scenario: Scenario, fitness: Fitness) -> Self {
+        Self {
+            scenario,
+            fitness,
+            population: 24,
+            generations: 30,
+            elites: 2,
+            tournament: 3,
+            mutation: 0.1,
+            min: Genome::from_genes([0.0; 3]),
+            max: Genome::from_genes([1.0, 1.0, 0.001]),
+            seed: 0,
+        }
+    }
+    fn evaluate_all(&self, genomes: Vec<Genome>) -> Vec<Candidate> {
+        let evaluate = |genome: Genome| Candidate {
+            genome,
+            score: self.scenario.evaluate(&genome, self.fitness),
+        };
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            genomes.into_par_iter().map(evaluate).collect()
+        }
+        #[cfg(not(feature = "parallel"))]
+        genomes.into_iter().map(evaluate).collect()
+    }
+    fn random_genome(&self, rng: &mut Rng) -> Genome {
+        let (min, max) = (self.min.genes(), self.max.genes());
+        Genome::from_genes(std::array::from_fn(|g| rng.range(min[g], max[g])))
+    }
+    /**
+    best of tournament randomly picked candidates,
+    the candidates are sorted best first
+     */
+    fn select<'a>(&self, rng: &mut Rng, sorted: &'a [Candidate]) -> &'a Candidate {
+        let best = (0..self.tournament.max(1))
+            .map(|_| (rng.next_u64() % sorted.len() as u64) as usize)
+            .min()
+            .unwrap();
+        &sorted[best]
+    }
+    fn child(&self, rng: &mut Rng, a: &Genome, b: &Genome) -> Genome {
+        let (min, max) = (self.min.genes(), self.max.genes());
+        let (a, b) = (a.genes(), b.genes());
+        Genome::from_genes(std::array::from_fn(|g| {
+            // blend crossover, reaching a little past both
+            // parents so the range does not only shrink
+            let t = rng.range(-0.25, 1.25);
+            let gene = a[g] + (b[g] - a[g]) * t;
+            let noise = gaussian(rng) * self.mutation * (max[g] - min[g]);
+            (gene + noise).clamp(min[g], max[g])
+        }))
+    }
+    /**
+    evolves the population and returns the best candidate
+    found, on_generation is called with the best so far
+    after each generation
+     */
+    pub fn run(&self, mut on_generation: impl FnMut(usize, &Candidate)) -> Candidate {
+        let mut rng = Rng::new(self.seed);
+        let population = self.population.max(1);
+        let genomes = (0..population)
+            .map(|_| self.random_genome(&mut rng))
+            .collect();
+        let mut candidates = self.evaluate_all(genomes);
+        for generation in 0..self.generations {
+            // stable, so ties keep their order and reruns match
+            candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+            on_generation(generation, &candidates[0]);
+            if generation + 1 == self.generations {
+                break;
+            }
+            let elites = self.elites.min(population);
+            let children = (elites..population)
+                .map(|_| {
+                    let a = self.select(&mut rng, &candidates).genome;
+                    let b = self.select(&mut rng, &candidates).genome;
+                    self.child(&mut rng, &a, &b)
+                })
+                .collect();
+            // elites keep their score, evaluation is deterministic
+            candidates.truncate(elites);
+            candidates.extend(self.evaluate_all(children));
+        }
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        candidates[0]
+    }
+}
+
+/**
+standard normal, with the box muller transform
+ */
+fn gaussian(rng: &mut Rng) -> f64 {
+    // 1 - x is in (0, 1], so the log is finite
+    let r = (-2.0 * (1.0 - rng.next_f64()).ln()).sqrt();
+    r * rng.range(0.0, std::f64::consts::TAU).cos()
+}
+
+#[test]
+fn test_fitness_score() {
+    // two boids flying right, one left, far apart
+    let boids = [
+        0.0, 0.0, 2.0, 0.0, //
+        3.0, 4.0, 1.0, 0.0, //
+        100.0, 0.0, -1.0, 0.0,
+    ];
+    let score = |fitness: Fitness| fitness.score(&boids, 10.0);
+    assert!((score(Fitness::Polarization) - 1.0 / 3.0).abs() < 1e-12);
+    assert_eq!(score(Fitness::Collisions { radius: 6.0 }), -1.0);
+    assert_eq!(score(Fitness::Collisions { radius: 5.0 }), 0.0);
+    assert_eq!(score(Fitness::Clusters { target: 2 }), 0.0);
+    assert_eq!(score(Fitness::Clusters { target: 5 }), -3.0);
+    let target = Vec2::new(0.0, 4.0);
+    let expected = -(4.0 + 3.0 + 100.0f64.hypot(4.0)) / 3.0;
+    assert!((score(Fitness::TargetDistance { target }) - expected).abs() < 1e-12);
+    assert_eq!(Fitness::Polarization.score(&[], 10.0), 0.0);
+}
+
+#[test]
+fn test_optimizer() {
+    let scenario = Scenario {
+        num_boids: 30,
+        warmup: 5,
+        ticks: 5,
+        runs: 1,
+        ..Scenario::default()
+    };
+    let mut optimizer = Optimizer::new(scenario, Fitness::Polarization);
+    optimizer.population = 6;
+    optimizer.generations = 4;
+
+    // elites carry over, so the best never gets worse
+    let mut history = Vec::new();
+    let best = optimizer.run(|_, best| history.push(best.score));
+    assert_eq!(history.len(), 4);
+    assert!(history.windows(2).all(|w| w[0] <= w[1]));
+    assert_eq!(best.score, history[3]);
+    let genes = best.genome.genes();
+    for (g, gene) in genes.iter().enumerate() {
+        assert!((optimizer.min.genes()[g]..=optimizer.max.genes()[g]).contains(gene));
+    }
+    assert_eq!(
+        optimizer
+            .scenario
+            .evaluate(&best.genome, Fitness::Polarization),
+        best.score
+    );
+    // reruns from the same seed match
+    assert_eq!(optimizer.run(|_, _| {}), best);
+
+    let json = best.to_json(Fitness::Polarization);
+    assert!(json.starts_with("{\n  \"fitness\": \"polarization\",\n"));
+    assert!(json.contains(&format!("\"cohesion_factor\": {}\n}}", genes[2])));
+}
@@ -0,0 +1,192 @@
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/**
+parameter a boid can have its own value of,
+instead of the one shared by the world
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoidParam {
+    MaxSpeed,
+    MinSpeed,
+    VisibleRange,
+    ProtectRange,
+    AvoidFactor,
+    AlignFactor,
+    CohesionFactor,
+}
+impl BoidParam {
+    const COUNT: usize = 7;
+
+    /**
+    speeds and ranges, which cannot be negative
+     */
+    pub fn is_magnitude(&self) -> bool {
+        matches!(
+            self,
+            BoidParam::MaxSpeed
+                | BoidParam::MinSpeed
+                | BoidParam::VisibleRange
+                | BoidParam::ProtectRange
+        )
+    }
+}
+
+/**
+value of every parameter for one boid
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoidParams {
+    pub max_speed: f64,
+    pub min_speed: f64,
+    pub visible_range: f64,
+    pub protect_range: f64,
+    pub avoid_factor: f64,
+    pub align_factor: f64,
+    pub cohesion_factor: f64,
+}
+impl BoidParams {
+    pub fn get(&self, param: BoidParam) -> f64 {
+        match param {
+            BoidParam::MaxSpeed => self.max_speed,
+            BoidParam::MinSpeed => self.min_speed,
+            BoidParam::VisibleRange => self.visible_range,
+            BoidParam::ProtectRange => self.protect_range,
+            BoidParam::AvoidFactor => self.avoid_factor,
+            BoidParam::AlignFactor => self.align_factor,
+            BoidParam::CohesionFactor => self.cohesion_factor,
+        }
+    }
+    fn get_mut(&mut self, param: BoidParam) -> &mut f64 {
+        match param {
+            BoidParam::MaxSpeed => &mut self.max_speed,
+            BoidParam::MinSpeed => &mut self.min_speed,
+            BoidParam::VisibleRange => &mut self.visible_range,
+            BoidParam::ProtectRange => &mut self.protect_range,
+            BoidParam::AvoidFactor => &mut self.avoid_factor,
+            BoidParam::AlignFactor => &mut self.align_factor,
+            BoidParam::CohesionFactor => &mut self.cohesion_factor,
+        }
+    }
+    /**
+    the neighbour query has to reach both ranges
+     */
+    pub fn query_range(&self) -> f64 {
+        f64::max(self.visible_range, self.protect_range)
+    }
+}
+
+/**
+parameters a boid does not share with the world
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Overrides {
+    values: [Option<f64>; BoidParam::COUNT],
+    // inherited mutations, multiplying whichever value the
+    // boid uses so the world's values still reach it
+    scales: [f64; BoidParam::COUNT],
+}
+impl Default for Overrides {
+    fn default() -> Self {
+        Self {
+            values: [None; BoidParam::COUNT],
+            scales: [1.0; BoidParam::COUNT],
+        }
+    }
+}
+impl Overrides {
+    /**
+    replaces the boid's value, None goes back to
+    the world's. either drops the scale
+     */
+    pub fn set(&mut self, param: BoidParam, value: Option<f64>) {
+        self.values[param as usize] = value;
+        self.scales[param as usize] = 1.0;
+    }
+    pub fn scale(&mut self, param: BoidParam, factor: f64) {
+        self.scales[param as usize] *= factor;
+    }
+    pub fn is_empty(&self) -> bool {
+        self.values.iter().all(Option::is_none) && self.scales.iter().all(|&s| s == 1.0)
+    }
+    /**
+    shared with every overridden parameter replaced
+    and every scale applied
+     */
+    pub fn apply(&self, shared: BoidParams) -> BoidParams {
+        let mut params = shared;
+        for (i, param) in PARAMS.iter().enumerate() {
+            let value = params.get_mut(*param);
+            *value = self.values[i].unwrap_or(*value) * self.scales[i];
+        }
+        // scaled speed limits can cross
+        params.min_speed = params.min_speed.min(params.max_speed);
+        params
+    }
+}
+
+/**
+every parameter, in the order of the enum
+ */
+pub const PARAMS: [BoidParam; BoidParam::COUNT] = [
+    BoidParam::MaxSpeed,
+    BoidParam::MinSpeed,
+    BoidParam::VisibleRange,
+    BoidParam::ProtectRange,
+    BoidParam::AvoidFactor,
+    BoidParam::AlignFactor,
+    BoidParam::CohesionFactor,
+];
+
+#[test]
+fn test_overrides() {
+    let shared = BoidParams {
+        max_speed: 6.0,
+        min_speed: 3.0,
+        visible_range: 40.0,
+        protect_range: 8.0,
+        avoid_factor: 0.05,
+        align_factor: 0.05,
+        cohesion_factor: 0.0005,
+    };
+    assert_eq!(shared.query_range(), 40.0);
+    for (i, &param) in PARAMS.iter().enumerate() {
+        assert_eq!(param as usize, i);
+        // the factors may push the other way
+        assert_eq!(param.is_magnitude(), i < 4);
+    }
+
+    let mut overrides = Overrides::default();
+    assert!(overrides.is_empty());
+    assert_eq!(overrides.apply(shared), shared);
+
+    overrides.set(BoidParam::MaxSpeed, Some(9.0));
+    overrides.set(BoidParam::ProtectRange, Some(50.0));
+    assert!(!overrides.is_empty());
+    let params = overrides.apply(shared);
+    assert_eq!(params.get(BoidParam::MaxSpeed), 9.0);
+    assert_eq!(params.get(BoidParam::MinSpeed), 3.0);
+    assert_eq!(params.query_range(), 50.0);
+
+    overrides.set(BoidParam::MaxSpeed, None);
+    overrides.set(BoidParam::ProtectRange, None);
+    assert!(overrides.is_empty());
+
+    // scales follow the shared value
+    overrides.scale(BoidParam::AlignFactor, 2.0);
+    assert!(!overrides.is_empty());
+    assert_eq!(overrides.apply(shared).align_factor, 0.1);
+    let faster = BoidParams {
+        align_factor: 0.5,
+        ..shared
+    };
+    assert_eq!(overrides.apply(faster).align_factor, 1.0);
+    overrides.set(BoidParam::AlignFactor, Some(0.2));
+    assert_eq!(overrides.apply(shared).align_factor, 0.2);
+    overrides.set(BoidParam::AlignFactor, None);
+    assert!(overrides.is_empty());
+
+    // and can push min_speed past max_speed
+    overrides.scale(BoidParam::MinSpeed, 3.0);
+    assert_eq!(overrides.apply(shared).min_speed, 6.0);
+}
@@ -0,0 +1,116 @@
+use crate::vec2::Vec2;
+
+/**
+polyline boids can follow, a closed path
+also joins the last point to the first
+ */
+#[derive(Clone, Debug)]
+pub struct Path {
+    points: Vec<Vec2>,
+    closed: bool,
+    // boids this close to the path are on it
+    radius: f64,
+}
+impl Path {
+    /**
+    None with fewer than 2 points
+     */
+    pub fn new(points: Vec<Vec2>, closed: bool, radius: f64) -> Option<Self> {
+        if points.len() < 2 {
+            return None;
+        }
+        Some(Self {
+            points,
+            closed,
+            radius,
+        })
+    }
+    fn segments(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        let closing = self
+            .closed
+            .then(|| (self.points[self.points.len() - 1], self.points[0]));
+        self.points.windows(2).map(|w| (w[0], w[1])).chain(closing)
+    }
+    /**
+    closest point on the path to pos, and the
+    direction of travel along the path there
+     */
+    pub fn project(&self, pos: Vec2) -> (Vec2, Vec2) {
+        let mut best = (f64::INFINITY, Vec2::zero(), Vec2::zero());
+        for (a, b) in self.segments() {
+            let seg = b - a;
+            let len_sq = seg.length_squared();
+            if len_sq == 0.0 {
+                // repeated point
+                continue;
+            }
+            let t = ((pos - a).dot(&seg) / len_sq).clamp(0.0, 1.0);
+            let point = a + seg * t;
+            let dist_sq = point.distance_squared(&pos);
+            if dist_sq < best.0 {
+                best = (dist_sq, point, seg / len_sq.sqrt());
+            }
+        }
+        (best.1, best.2)
+    }
+    /**
+    where a boid at pos moving at vel should head, None while
+    its position lookahead ticks from now is still on the path
+     */
+    pub fn target(&self, pos: Vec2, vel: Vec2, lookahead: f64) -> Option<Vec2> {
+        let predicted = pos + vel * lookahead;
+        let (point, dir) = self.project(predicted);
+        if point.distance(&predicted) <= self.radius {
+            return None;
+        }
+        // a little further along, so boids move with the path
+        // instead of just towards it
+        Some(point + dir * (vel.length() * lookahead))
+    }
+}
+
+#[test]
+fn test_path_project() {
+    let points = vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(10.0, 0.0),
+        Vec2::new(10.0, 10.0),
+    ];
+    assert!(Path::new(points[..1].to_vec(), false, 1.0).is_none());
+
+    let open = Path::new(points.clone(), false, 1.0).unwrap();
+    assert_eq!(
+        open.project(Vec2::new(4.0, 3.0)),
+        (Vec2::new(4.0, 0.0), Vec2::new(1.0, 0.0))
+    );
+    assert_eq!(
+        open.project(Vec2::new(12.0, 6.0)),
+        (Vec2::new(10.0, 6.0), Vec2::new(0.0, 1.0))
+    );
+    // past the end of an open path
+    assert_eq!(
+        open.project(Vec2::new(10.0, 15.0)),
+        (Vec2::new(10.0, 10.0), Vec2::new(0.0, 1.0))
+    );
+
+    // the closing segment runs back along the diagonal
+    let closed = Path::new(points, true, 1.0).unwrap();
+    let (point, dir) = closed.project(Vec2::new(3.0, 7.0));
+    assert_eq!(point, Vec2::new(5.0, 5.0));
+    assert!((dir - Vec2::new(-1.0, -1.0).normalize()).length() < 1e-12);
+}
+
+#[test]
+fn test_path_target() {
+    let path = Path::new(vec![Vec2::zero(), Vec2::new(100.0, 0.0)], false, 2.0).unwrap();
+    // predicted to stay within the radius
+    assert_eq!(
+        path.target(Vec2::new(10.0, 1.0), Vec2::new(1.0, 0.0), 5.0),
+        None
+    );
+    // drifting away, aims back at the path further along
+    assert_eq!(
+        path.target(Vec2::new(10.0, 1.0), Vec2::new(0.0, 1.0), 5.0),
+        Some(Vec2::new(15.0, 0.0))
+    );
+}
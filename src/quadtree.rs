@@ -1,34 +1,187 @@
 use js_sys::Int32Array;
+use wasm_bindgen::prelude::wasm_bindgen;
 
-use crate::vec2::{Position, Vec2};
-use std::collections::VecDeque;
+use crate::{
+    spatial_index::SpatialIndex,
+    vec2::{Position, Vec2},
+};
+use std::{cmp::Ordering, collections::BinaryHeap, error::Error, fmt};
 
 /**
-checks for intersection between a circle
-and an axis-aligned rectangle
+squared distance from a point to the closest
+point of an axis-aligned rectangle, 0 if inside
  */
-fn isect_circle_rect(center: Vec2, radius: f64, top_left: Vec2, dims: Vec2) -> bool {
+pub fn dist_squared_point_rect(point: Vec2, top_left: Vec2, dims: Vec2) -> f64 {
     // https://www.jeffreythompson.org/collision-detection/circle-rect.php
-    let test_x = if center.x < top_left.x {
+    let test_x = if point.x < top_left.x {
         top_left.x
-    } else if center.x > top_left.x + dims.x {
+    } else if point.x > top_left.x + dims.x {
         top_left.x + dims.x
     } else {
-        center.x
+        point.x
     };
-    let test_y = if center.y < top_left.y {
+    let test_y = if point.y < top_left.y {
         top_left.y
-    } else if center.y > top_left.y + dims.y {
+    } else if point.y > top_left.y + dims.y {
         top_left.y + dims.y
     } else {
-        center.y
+        point.y
     };
 
-    let dist_sq = center.distance_squared(&Vec2::new(test_x, test_y));
-    if dist_sq <= radius * radius {
-        true
+    point.distance_squared(&Vec2::new(test_x, test_y))
+}
+
+/**
+checks for intersection between a circle
+and an axis-aligned rectangle
+ */
+fn isect_circle_rect(center: Vec2, radius: f64, top_left: Vec2, dims: Vec2) -> bool {
+    dist_squared_point_rect(center, top_left, dims) <= radius * radius
+}
+
+fn rect_contains(top_left: Vec2, dims: Vec2, pos: Vec2) -> bool {
+    pos.x >= top_left.x
+        && pos.x <= top_left.x + dims.x
+        && pos.y >= top_left.y
+        && pos.y <= top_left.y + dims.y
+}
+
+/**
+doubles a rectangle towards pos, returns the new
+rectangle and whether it grew up and to the left,
+or None if it cannot grow
+ */
+fn grow_rect(top_left: Vec2, dims: Vec2, pos: Vec2) -> Option<(Vec2, Vec2, bool, bool)> {
+    let new_dims = dims * 2.0;
+    if !(dims.x > 0.0 && dims.y > 0.0 && new_dims.x.is_finite() && new_dims.y.is_finite()) {
+        return None;
+    }
+    let top = pos.y < top_left.y;
+    let left = pos.x < top_left.x;
+    let new_top_left = Vec2::new(
+        if left {
+            top_left.x - dims.x
+        } else {
+            top_left.x
+        },
+        if top { top_left.y - dims.y } else { top_left.y },
+    );
+    Some((new_top_left, new_dims, top, left))
+}
+
+/**
+distances along a normalized ray at which it enters and
+leaves an axis-aligned rectangle, None if it misses
+ */
+pub fn ray_rect_range(origin: Vec2, dir: Vec2, top_left: Vec2, dims: Vec2) -> Option<(f64, f64)> {
+    // slab method, one axis at a time
+    let mut t_min = f64::NEG_INFINITY;
+    let mut t_max = f64::INFINITY;
+    for (o, d, lo, hi) in [
+        (origin.x, dir.x, top_left.x, top_left.x + dims.x),
+        (origin.y, dir.y, top_left.y, top_left.y + dims.y),
+    ] {
+        if d == 0.0 {
+            // parallel to the slab, must start inside it
+            if o < lo || o > hi {
+                return None;
+            }
+        } else {
+            let t1 = (lo - o) / d;
+            let t2 = (hi - o) / d;
+            t_min = t_min.max(t1.min(t2));
+            t_max = t_max.min(t1.max(t2));
+        }
+    }
+    if t_min > t_max {
+        None
     } else {
-        false
+        Some((t_min, t_max))
+    }
+}
+
+/**
+distance along a normalized ray to the first point
+of a circle, 0 if the ray starts inside it
+ */
+pub fn ray_circle_dist(origin: Vec2, dir: Vec2, center: Vec2, radius: f64) -> Option<f64> {
+    let oc = origin - center;
+    let c = oc.length_squared() - radius * radius;
+    if c <= 0.0 {
+        return Some(0.0);
+    }
+    let b = oc.dot(&dir);
+    if b > 0.0 {
+        // outside and pointing away
+        return None;
+    }
+    let disc = b * b - c;
+    if disc < 0.0 {
+        None
+    } else {
+        Some(-b - disc.sqrt())
+    }
+}
+
+/**
+spreads the low 16 bits of v over the even bits
+ */
+fn spread_bits(v: u32) -> u32 {
+    let mut v = v & 0xffff;
+    v = (v | (v << 8)) & 0x00ff_00ff;
+    v = (v | (v << 4)) & 0x0f0f_0f0f;
+    v = (v | (v << 2)) & 0x3333_3333;
+    v = (v | (v << 1)) & 0x5555_5555;
+    v
+}
+
+/**
+position of a point along the z-order curve through a
+rectangle, 16 bits per axis with y in the odd bits.
+sorting by it groups points by quadrant at every depth,
+in the order top left, top right, bottom left, bottom right
+ */
+pub fn morton_key(pos: Vec2, top_left: Vec2, dims: Vec2) -> u32 {
+    const CELLS: f64 = 65536.0;
+    // as saturates, so points outside the
+    // rectangle land on its closest edge
+    let x = (((pos.x - top_left.x) / dims.x * CELLS) as u32).min(0xffff);
+    let y = (((pos.y - top_left.y) / dims.y * CELLS) as u32).min(0xffff);
+    spread_bits(x) | (spread_bits(y) << 1)
+}
+
+/**
+entry of the nearest neighbour priority queue,
+either a whole node or a single item of a node
+ */
+#[derive(Clone, Copy)]
+enum NearestEntry {
+    Node(usize),
+    Item(usize, usize),
+}
+
+/**
+min-heap wrapper ordered by squared distance
+ */
+struct NearestCandidate {
+    dist_sq: f64,
+    entry: NearestEntry,
+}
+impl PartialEq for NearestCandidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for NearestCandidate {}
+impl PartialOrd for NearestCandidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for NearestCandidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // reversed so BinaryHeap pops the closest first
+        other.dist_sq.total_cmp(&self.dist_sq)
     }
 }
 
@@ -76,6 +229,59 @@ where
     }
 }
 
+/**
+summary of the shape of a quadtree,
+items are counted at the node holding them
+ */
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuadTreeStats {
+    pub node_count: usize,
+    pub leaf_count: usize,
+    // root is depth 0
+    pub max_depth: usize,
+    // internal nodes hold items too,
+    // they are not counted here
+    pub avg_leaf_items: f64,
+    items_per_depth: Vec<usize>,
+}
+#[wasm_bindgen]
+impl QuadTreeStats {
+    /**
+    number of entries in the items per depth histogram
+     */
+    pub fn depths(&self) -> usize {
+        self.items_per_depth.len()
+    }
+    pub fn items_at_depth(&self, depth: usize) -> usize {
+        self.items_per_depth.get(depth).copied().unwrap_or(0)
+    }
+}
+
+/**
+reason an item could not be inserted
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PushError {
+    // position has a NaN or infinite coordinate
+    NotFinite,
+    // outside the borders of a strict tree,
+    // or too far away for an expanding tree to reach
+    OutOfBounds,
+}
+impl fmt::Display for PushError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushError::NotFinite => write!(f, "item position is not finite"),
+            PushError::OutOfBounds => write!(f, "item position is outside the quadtree"),
+        }
+    }
+}
+impl Error for PushError {}
+
+pub const DEFAULT_MAX_POINTS: usize = 4;
+pub const DEFAULT_MAX_DEPTH: usize = 16;
+
 pub struct QuadTree<T>
 where
     T: Position + Copy,
@@ -85,6 +291,12 @@ where
     pub border_dims: Vec2,
     nodes: Vec<QuadTreeNode<T>>,
     max_points: usize,
+    // nodes at this depth never subdivide and
+    // hold any number of items, root is depth 0
+    max_depth: usize,
+    // when set, pushing outside the borders grows the
+    // tree instead of rejecting the item
+    auto_expand: bool,
     num_items: usize,
 }
 impl<T> QuadTree<T>
@@ -92,52 +304,155 @@ where
     T: Position + Copy,
 {
     pub fn new(border_top_left: Vec2, border_dims: Vec2) -> Self {
+        Self::with_params(
+            border_top_left,
+            border_dims,
+            DEFAULT_MAX_POINTS,
+            DEFAULT_MAX_DEPTH,
+        )
+    }
+    /**
+    max_points is the number of items a node holds
+    before subdividing, and is clamped to at least 1
+     */
+    pub fn with_params(
+        border_top_left: Vec2,
+        border_dims: Vec2,
+        max_points: usize,
+        max_depth: usize,
+    ) -> Self {
         Self {
             border_top_left,
             border_dims,
             nodes: vec![QuadTreeNode::new(border_top_left, border_dims)],
-            max_points: 4,
+            max_points: max_points.max(1),
+            max_depth,
+            auto_expand: false,
             num_items: 0,
         }
     }
     pub fn len(&self) -> usize {
         self.num_items
     }
-    #[cfg(test)]
-    fn max_points(&self) -> usize {
+    pub fn max_points(&self) -> usize {
         self.max_points
     }
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+    pub fn auto_expand(&self) -> bool {
+        self.auto_expand
+    }
+    pub fn set_auto_expand(&mut self, auto_expand: bool) {
+        self.auto_expand = auto_expand;
+    }
     #[cfg(test)]
     fn get_nodes(&self) -> Vec<QuadTreeNode<T>> {
         self.nodes.to_vec()
     }
     pub fn query_circle(&self, center: Vec2, radius: f64) -> Vec<T> {
-        // TODO: change circle/rect intersect checking to the
-        // beginning of the loop, so that it checks with the root node
         let mut ret = Vec::new();
-        let mut q: VecDeque<usize> = [0].into();
+        self.query_circle_into(center, radius, &mut Vec::new(), &mut ret);
+        ret
+    }
+    /**
+    appends every item inside the circle to buf,
+    existing contents of buf are kept
+     */
+    pub fn query_circle_into(
+        &self,
+        center: Vec2,
+        radius: f64,
+        stack: &mut Vec<usize>,
+        buf: &mut Vec<T>,
+    ) {
+        self.query_circle_with(center, radius, stack, |item| buf.push(*item));
+    }
+    /**
+    calls f on every item inside the circle. stack holds the
+    nodes still to visit, callers keep one between queries
+    so that they do not allocate
+     */
+    pub fn query_circle_with<F>(&self, center: Vec2, radius: f64, stack: &mut Vec<usize>, mut f: F)
+    where
+        F: FnMut(&T),
+    {
+        if self.num_items == 0 {
+            return;
+        }
         let radius_sq = radius * radius;
-        while !q.is_empty() {
-            let cur = q.pop_front().unwrap();
-            for item in &self.nodes[cur].items {
+        stack.clear();
+        stack.push(0);
+        while let Some(cur) = stack.pop() {
+            let node = &self.nodes[cur];
+            if !isect_circle_rect(center, radius, node.rect_tl, node.rect_dims) {
+                continue;
+            }
+            for item in &node.items {
                 if item.pos().distance_squared(&center) <= radius_sq {
-                    ret.push(*item);
+                    f(item);
                 }
             }
-
-            // if circle intersects the bounding rectangle
-            // of any of the four child nodes, repeat loop
-            for child_idx in &self.nodes[cur].children() {
-                if *child_idx == 0 || self.nodes[*child_idx].len() == 0 {
-                    continue;
+            // reversed so that tl is visited first
+            for child_idx in [node.br_idx, node.bl_idx, node.tr_idx, node.tl_idx] {
+                if child_idx != 0 {
+                    stack.push(child_idx);
+                }
+            }
+        }
+    }
+    /**
+    returns the item closest to point,
+    or None if the tree is empty
+     */
+    pub fn nearest(&self, point: Vec2) -> Option<T> {
+        self.k_nearest(point, 1).pop()
+    }
+    /**
+    returns up to k items closest to point,
+    sorted from closest to furthest
+     */
+    pub fn k_nearest(&self, point: Vec2, k: usize) -> Vec<T> {
+        // best-first search: nodes are keyed by the distance to
+        // their rectangle, which is a lower bound for every item
+        // inside them, so items are popped in order of distance
+        let mut ret = Vec::with_capacity(k.min(self.num_items));
+        if k == 0 {
+            return ret;
+        }
+        let mut heap = BinaryHeap::new();
+        heap.push(NearestCandidate {
+            dist_sq: 0.0,
+            entry: NearestEntry::Node(0),
+        });
+        while let Some(NearestCandidate { entry, .. }) = heap.pop() {
+            match entry {
+                NearestEntry::Item(node_idx, item_idx) => {
+                    ret.push(self.nodes[node_idx].items[item_idx]);
+                    if ret.len() == k {
+                        break;
+                    }
                 }
-                if isect_circle_rect(
-                    center,
-                    radius,
-                    self.nodes[*child_idx].rect_tl,
-                    self.nodes[*child_idx].rect_dims,
-                ) {
-                    q.push_back(*child_idx);
+                NearestEntry::Node(cur) => {
+                    for (item_idx, item) in self.nodes[cur].items.iter().enumerate() {
+                        heap.push(NearestCandidate {
+                            dist_sq: item.pos().distance_squared(&point),
+                            entry: NearestEntry::Item(cur, item_idx),
+                        });
+                    }
+                    for child_idx in &self.nodes[cur].children() {
+                        if *child_idx == 0 {
+                            continue;
+                        }
+                        heap.push(NearestCandidate {
+                            dist_sq: dist_squared_point_rect(
+                                point,
+                                self.nodes[*child_idx].rect_tl,
+                                self.nodes[*child_idx].rect_dims,
+                            ),
+                            entry: NearestEntry::Node(*child_idx),
+                        });
+                    }
                 }
             }
         }
@@ -156,26 +471,77 @@ where
         }
         ret
     }
+    fn contains(&self, pos: Vec2) -> bool {
+        rect_contains(self.border_top_left, self.border_dims, pos)
+    }
+    /**
+    doubles the root towards pos until pos is inside,
+    the old root becomes a quadrant of the new one
+     */
+    fn expand_towards(&mut self, pos: Vec2) -> Result<(), PushError> {
+        // make sure pos is reachable before touching the tree
+        let (mut top_left, mut dims) = (self.border_top_left, self.border_dims);
+        while !rect_contains(top_left, dims, pos) {
+            (top_left, dims, _, _) =
+                grow_rect(top_left, dims, pos).ok_or(PushError::OutOfBounds)?;
+        }
+
+        while !self.contains(pos) {
+            // cannot fail, checked above
+            let (new_top_left, new_dims, top, left) =
+                grow_rect(self.border_top_left, self.border_dims, pos).unwrap();
+
+            // root has to stay at nodes[0], so move the old
+            // root to the end and put the new root in its place
+            let old_root_idx = self.nodes.len();
+            let old_root = std::mem::replace(
+                &mut self.nodes[0],
+                QuadTreeNode::new(new_top_left, new_dims),
+            );
+            self.nodes.push(old_root);
+            // growing left means the old root is on the right
+            let root = &mut self.nodes[0];
+            match (top, left) {
+                (true, true) => root.br_idx = old_root_idx,
+                (true, false) => root.bl_idx = old_root_idx,
+                (false, true) => root.tr_idx = old_root_idx,
+                (false, false) => root.tl_idx = old_root_idx,
+            }
+            self.border_top_left = new_top_left;
+            self.border_dims = new_dims;
+        }
+        Ok(())
+    }
+    /**
+    returns false if the item could not be inserted,
+    see try_push for the reason
+     */
     pub fn push(&mut self, item: &T) -> bool {
-        // TODO: bug where adding an item out of unchangeable bounds
-        // doesnt update the item count
-        if item.x() < self.border_top_left.x
-            || item.x() > self.border_top_left.x + self.border_dims.x
-            || item.y() < self.border_top_left.y
-            || item.y() > self.border_top_left.y + self.border_dims.y
-        {
-            return false;
+        self.try_push(item).is_ok()
+    }
+    pub fn try_push(&mut self, item: &T) -> Result<(), PushError> {
+        if !item.x().is_finite() || !item.y().is_finite() {
+            return Err(PushError::NotFinite);
+        }
+        if !self.contains(item.pos()) {
+            if !self.auto_expand {
+                return Err(PushError::OutOfBounds);
+            }
+            self.expand_towards(item.pos())?;
         }
 
         // values for root node
         let mut cur_idx = 0;
+        let mut depth = 0;
         let mut top_left = self.border_top_left;
         let mut bot_right = self.border_top_left + self.border_dims;
         loop {
-            if self.nodes[cur_idx].len() < self.max_points {
+            // without a depth limit, more than max_points
+            // coincident items would subdivide forever
+            if self.nodes[cur_idx].len() < self.max_points || depth >= self.max_depth {
                 self.nodes[cur_idx].push(item);
                 self.num_items += 1;
-                return true;
+                return Ok(());
             }
 
             // x is left/right
@@ -198,6 +564,8 @@ where
                 top_left.y = mid.y;
             }
 
+            depth += 1;
+
             // theres probably a more
             // elegant way to do this
             let half_dims = self.nodes[cur_idx].rect_dims / 2.0;
@@ -246,6 +614,108 @@ where
             }
         }
     }
+    /**
+    replaces the contents of the tree with items, giving the same tree
+    as a reset followed by pushing them in order but in a single pass.
+    fastest when items are sorted by morton_key over the borders.
+    fails without changing the tree if any item does not fit
+     */
+    pub fn build(
+        &mut self,
+        border_top_left: Vec2,
+        border_dims: Vec2,
+        items: &mut [T],
+    ) -> Result<(), PushError> {
+        for item in items.iter() {
+            if !item.x().is_finite() || !item.y().is_finite() {
+                return Err(PushError::NotFinite);
+            }
+            if !rect_contains(border_top_left, border_dims, item.pos()) {
+                return Err(PushError::OutOfBounds);
+            }
+        }
+        self.reset(border_top_left, border_dims);
+        self.num_items = items.len();
+        self.build_node(0, 0, border_top_left, border_top_left + border_dims, items);
+        Ok(())
+    }
+    fn build_node(
+        &mut self,
+        cur: usize,
+        depth: usize,
+        top_left: Vec2,
+        bot_right: Vec2,
+        items: &mut [T],
+    ) {
+        // a node keeps the first items to reach it,
+        // the rest are passed on to its quadrants
+        let keep = if depth >= self.max_depth {
+            items.len()
+        } else {
+            items.len().min(self.max_points)
+        };
+        let (here, rest) = items.split_at_mut(keep);
+        self.nodes[cur].items.extend_from_slice(here);
+        if rest.is_empty() {
+            return;
+        }
+
+        // same split as push, ties go top and left
+        let mid = (top_left + bot_right) / 2.0;
+        let quadrant = |item: &T| 2 * usize::from(item.y() > mid.y) + usize::from(item.x() > mid.x);
+        // a stable sort keeps the order within each quadrant, so
+        // the result still matches pushing in the original order
+        if !rest.windows(2).all(|w| quadrant(&w[0]) <= quadrant(&w[1])) {
+            rest.sort_by_key(quadrant);
+        }
+
+        let rect_tl = self.nodes[cur].rect_tl;
+        let half_dims = self.nodes[cur].rect_dims / 2.0;
+        let mut start = 0;
+        for q in 0..4 {
+            let end = rest.partition_point(|item| quadrant(item) <= q);
+            if start == end {
+                continue;
+            }
+            let (top, left) = (q < 2, q % 2 == 0);
+            let child_idx = self.nodes.len();
+            let child_tl = match (top, left) {
+                (true, true) => rect_tl,
+                (true, false) => rect_tl + half_dims.keep_x(),
+                (false, true) => rect_tl + half_dims.keep_y(),
+                (false, false) => rect_tl + half_dims,
+            };
+            self.nodes.push(QuadTreeNode::new(child_tl, half_dims));
+            let node = &mut self.nodes[cur];
+            match (top, left) {
+                (true, true) => node.tl_idx = child_idx,
+                (true, false) => node.tr_idx = child_idx,
+                (false, true) => node.bl_idx = child_idx,
+                (false, false) => node.br_idx = child_idx,
+            }
+
+            let mut child_top_left = top_left;
+            let mut child_bot_right = bot_right;
+            if left {
+                child_bot_right.x = mid.x;
+            } else {
+                child_top_left.x = mid.x;
+            }
+            if top {
+                child_bot_right.y = mid.y;
+            } else {
+                child_top_left.y = mid.y;
+            }
+            self.build_node(
+                child_idx,
+                depth + 1,
+                child_top_left,
+                child_bot_right,
+                &mut rest[start..end],
+            );
+            start = end;
+        }
+    }
     pub fn reset(&mut self, border_top_left: Vec2, border_dims: Vec2) {
         self.nodes.clear();
         self.nodes
@@ -255,6 +725,130 @@ where
         self.num_items = 0;
     }
 
+    /**
+    first item hit by a ray, with the distance along the ray to
+    it. dir does not need to be normalized. the nodes only bound
+    item positions, so every item is a circle of the same given
+    radius, items of different sizes belong in a LooseQuadTree
+     */
+    pub fn raycast(&self, origin: Vec2, dir: Vec2, max_dist: f64, radius: f64) -> Option<(T, f64)> {
+        let len = dir.length();
+        if self.num_items == 0 || len == 0.0 || !len.is_finite() {
+            return None;
+        }
+        let dir = dir / len;
+        let mut best = None;
+        let mut best_dist = max_dist;
+        self.raycast_node(0, origin, dir, radius, &mut best, &mut best_dist);
+        best.map(|item| (item, best_dist))
+    }
+    fn raycast_node(
+        &self,
+        cur: usize,
+        origin: Vec2,
+        dir: Vec2,
+        radius: f64,
+        best: &mut Option<T>,
+        best_dist: &mut f64,
+    ) {
+        let node = &self.nodes[cur];
+        for item in &node.items {
+            if let Some(dist) = ray_circle_dist(origin, dir, item.pos(), radius) {
+                if dist <= *best_dist {
+                    *best = Some(*item);
+                    *best_dist = dist;
+                }
+            }
+        }
+
+        // visit the children the ray crosses from front to back,
+        // rects are grown by radius since circles stick out of them
+        let mut crossed = [(0, 0.0); 4];
+        let mut num_crossed = 0;
+        for child_idx in node.children() {
+            if child_idx == 0 {
+                continue;
+            }
+            let child = &self.nodes[child_idx];
+            if let Some((enter, exit)) = ray_rect_range(
+                origin,
+                dir,
+                child.rect_tl - radius,
+                child.rect_dims + radius * 2.0,
+            ) {
+                if exit >= 0.0 && enter <= *best_dist {
+                    crossed[num_crossed] = (child_idx, enter.max(0.0));
+                    num_crossed += 1;
+                }
+            }
+        }
+        let crossed = &mut crossed[..num_crossed];
+        crossed.sort_by(|a, b| a.1.total_cmp(&b.1));
+        for (child_idx, enter) in crossed {
+            // a closer hit was found in an earlier child
+            if *enter > *best_dist {
+                break;
+            }
+            self.raycast_node(*child_idx, origin, dir, radius, best, best_dist);
+        }
+    }
+    #[cfg(test)]
+    fn raycast_brute_force(
+        &self,
+        origin: Vec2,
+        dir: Vec2,
+        max_dist: f64,
+        radius: f64,
+    ) -> Option<f64> {
+        let dir = dir.normalize();
+        let mut ret = None;
+        for node in &self.nodes {
+            for item in &node.items {
+                if let Some(dist) = ray_circle_dist(origin, dir, item.pos(), radius) {
+                    if dist <= max_dist && ret.map_or(true, |best| dist < best) {
+                        ret = Some(dist);
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    pub fn stats(&self) -> QuadTreeStats {
+        let mut stats = QuadTreeStats {
+            node_count: 0,
+            leaf_count: 0,
+            max_depth: 0,
+            avg_leaf_items: 0.0,
+            items_per_depth: Vec::new(),
+        };
+        let mut leaf_items = 0;
+        let mut stack = vec![(0, 0)];
+        while let Some((cur, depth)) = stack.pop() {
+            let node = &self.nodes[cur];
+            stats.node_count += 1;
+            stats.max_depth = stats.max_depth.max(depth);
+            if stats.items_per_depth.len() <= depth {
+                stats.items_per_depth.resize(depth + 1, 0);
+            }
+            stats.items_per_depth[depth] += node.len();
+
+            let mut is_leaf = true;
+            for child_idx in node.children() {
+                if child_idx != 0 {
+                    is_leaf = false;
+                    stack.push((child_idx, depth + 1));
+                }
+            }
+            if is_leaf {
+                stats.leaf_count += 1;
+                leaf_items += node.len();
+            }
+        }
+        stats.avg_leaf_items = leaf_items as f64 / stats.leaf_count as f64;
+        stats
+    }
+
     pub fn node_len(&self, node_idx: usize) -> usize {
         self.nodes[node_idx].len()
     }
@@ -270,6 +864,27 @@ where
     }
 }
 
+impl<T> SpatialIndex<T> for QuadTree<T>
+where
+    T: Position + Copy,
+{
+    fn len(&self) -> usize {
+        QuadTree::len(self)
+    }
+    fn reset(&mut self, border_top_left: Vec2, border_dims: Vec2) {
+        QuadTree::reset(self, border_top_left, border_dims)
+    }
+    fn push(&mut self, item: &T) -> bool {
+        QuadTree::push(self, item)
+    }
+    fn query_circle_with<F>(&self, center: Vec2, radius: f64, stack: &mut Vec<usize>, f: F)
+    where
+        F: FnMut(&T),
+    {
+        QuadTree::query_circle_with(self, center, radius, stack, f)
+    }
+}
+
 #[test]
 fn test_quadtree_new() {
     let mut tree = QuadTree::new(Vec2::zero(), Vec2::from(123.0));
@@ -388,3 +1003,360 @@ fn test_quadtree_query_circle() {
             .len()
     );
 }
+
+#[test]
+fn test_quadtree_nearest() {
+    let mut tree = QuadTree::new(Vec2::zero(), Vec2::from(100.0));
+    assert!(tree.nearest(Vec2::from(50.0)).is_none());
+    assert!(tree.k_nearest(Vec2::from(50.0), 3).is_empty());
+
+    for i in 0..=20 {
+        for j in 0..=20 {
+            // offset so there are no distance ties
+            tree.push(&Vec2::new(i as f64 * 5.0 + j as f64 * 0.01, j as f64 * 4.9));
+        }
+    }
+    let queries = [
+        Vec2::new(50.3, 50.7),
+        Vec2::new(0.1, 99.2),
+        Vec2::new(-20.0, 42.0),
+        Vec2::new(133.3, 1.2),
+    ];
+    for query in queries {
+        let mut expected = tree.query_circle_brute_force(query, f64::INFINITY);
+        expected.sort_by(|a, b| {
+            a.distance_squared(&query)
+                .total_cmp(&b.distance_squared(&query))
+        });
+
+        assert_eq!(tree.nearest(query), Some(expected[0]));
+        assert_eq!(tree.k_nearest(query, 0), Vec::new());
+        assert_eq!(tree.k_nearest(query, 7), expected[..7].to_vec());
+        assert_eq!(tree.k_nearest(query, 1000), expected);
+    }
+}
+
+#[test]
+fn test_quadtree_query_circle_with() {
+    let mut tree = QuadTree::new(Vec2::zero(), Vec2::from(10.0));
+    for i in 0..=10 {
+        for j in 0..=10 {
+            tree.push(&Vec2::new(i as f64, j as f64));
+        }
+    }
+
+    let mut stack = Vec::new();
+    let mut buf = vec![Vec2::from(-1.0)];
+    tree.query_circle_into(Vec2::new(4.2, 6.1), 3.3, &mut stack, &mut buf);
+    // existing contents are kept
+    assert_eq!(buf[0], Vec2::from(-1.0));
+    assert_eq!(
+        buf.len() - 1,
+        tree.query_circle_brute_force(Vec2::new(4.2, 6.1), 3.3)
+            .len()
+    );
+
+    let mut count = 0;
+    tree.query_circle_with(Vec2::new(4.2, 6.1), 3.3, &mut stack, |item| {
+        assert!(item.distance(&Vec2::new(4.2, 6.1)) <= 3.3);
+        count += 1;
+    });
+    assert_eq!(count, buf.len() - 1);
+
+    // a query inside another one needs a stack of its own
+    let mut inner = Vec::new();
+    let mut pairs = 0;
+    tree.query_circle_with(Vec2::new(4.2, 6.1), 3.3, &mut stack, |item| {
+        tree.query_circle_with(*item, 1.0, &mut inner, |_| pairs += 1);
+    });
+    // every point sees itself and up to four neighbours
+    assert!(pairs > count && pairs <= 5 * count);
+
+    // circle entirely outside of the root
+    tree.query_circle_with(Vec2::from(-50.0), 3.0, &mut stack, |_| panic!());
+}
+
+#[test]
+fn test_quadtree_max_depth() {
+    // coincident items would subdivide forever without a depth limit
+    let mut tree = QuadTree::with_params(Vec2::zero(), Vec2::from(10.0), 2, 3);
+    for _ in 0..100 {
+        assert!(tree.push(&Vec2::from(3.3)));
+    }
+    assert_eq!(tree.len(), 100);
+
+    // one node per depth, the last one holds the rest
+    let nodes = tree.get_nodes();
+    assert_eq!(nodes.len(), 4);
+    assert_eq!(nodes[3].get_items().len(), 100 - 2 * 3);
+    assert_eq!(nodes[3].rect_dims, Vec2::from(10.0 / 8.0));
+    assert_eq!(tree.query_circle(Vec2::from(3.3), 0.0).len(), 100);
+
+    // a zero capacity tree still stores items
+    let mut tree = QuadTree::with_params(Vec2::zero(), Vec2::from(10.0), 0, 0);
+    assert!(tree.push(&Vec2::from(1.0)));
+    assert!(tree.push(&Vec2::from(9.0)));
+    assert_eq!(tree.max_points(), 1);
+    assert_eq!(tree.get_nodes().len(), 1);
+}
+
+#[test]
+fn test_quadtree_stats() {
+    let mut tree = QuadTree::new(Vec2::zero(), Vec2::from(10.0));
+    let stats = tree.stats();
+    assert_eq!(stats.node_count, 1);
+    assert_eq!(stats.leaf_count, 1);
+    assert_eq!(stats.max_depth, 0);
+    assert_eq!(stats.avg_leaf_items, 0.0);
+    assert_eq!(stats.depths(), 1);
+    assert_eq!(stats.items_at_depth(0), 0);
+
+    // same layout as test_quadtree_child_nodes
+    for _ in 0..tree.max_points() {
+        tree.push(&Vec2::from(0.1));
+    }
+    tree.push(&Vec2::new(3.9, 4.5));
+    tree.push(&Vec2::new(9.4, 6.1));
+    tree.push(&Vec2::new(0.9, 9.6));
+    tree.push(&Vec2::new(7.7, 2.5));
+    // fill the top left quadrant and go one level deeper
+    tree.push(&Vec2::new(1.0, 1.0));
+    tree.push(&Vec2::new(1.0, 2.0));
+    tree.push(&Vec2::new(2.0, 1.0));
+    tree.push(&Vec2::new(4.0, 4.0));
+
+    let stats = tree.stats();
+    assert_eq!(stats.node_count, 6);
+    assert_eq!(stats.leaf_count, 4);
+    assert_eq!(stats.max_depth, 2);
+    assert_eq!(stats.avg_leaf_items, 1.0);
+    assert_eq!(stats.depths(), 3);
+    assert_eq!(stats.items_at_depth(0), 4);
+    assert_eq!(stats.items_at_depth(1), 7);
+    assert_eq!(stats.items_at_depth(2), 1);
+    assert_eq!(stats.items_at_depth(3), 0);
+}
+
+#[test]
+fn test_quadtree_try_push() {
+    let mut tree = QuadTree::new(Vec2::zero(), Vec2::from(50.0));
+    assert_eq!(tree.try_push(&Vec2::from(25.0)), Ok(()));
+    assert_eq!(
+        tree.try_push(&Vec2::new(7.0, 50.3)),
+        Err(PushError::OutOfBounds)
+    );
+    assert_eq!(
+        tree.try_push(&Vec2::new(f64::NAN, 3.0)),
+        Err(PushError::NotFinite)
+    );
+    assert_eq!(
+        tree.try_push(&Vec2::new(3.0, f64::INFINITY)),
+        Err(PushError::NotFinite)
+    );
+    assert_eq!(tree.len(), 1);
+    assert_eq!(tree.border_dims, Vec2::from(50.0));
+}
+
+#[test]
+fn test_quadtree_auto_expand() {
+    let mut tree = QuadTree::new(Vec2::zero(), Vec2::from(10.0));
+    tree.set_auto_expand(true);
+    for i in 0..=10 {
+        for j in 0..=10 {
+            tree.push(&Vec2::new(i as f64, j as f64));
+        }
+    }
+
+    // grow up and left once
+    assert!(tree.push(&Vec2::new(-3.0, -7.5)));
+    assert_eq!(tree.border_top_left, Vec2::from(-10.0));
+    assert_eq!(tree.border_dims, Vec2::from(20.0));
+    // old root is now the bottom right quadrant
+    let nodes = tree.get_nodes();
+    assert_eq!(nodes[0].children(), vec![0, 0, 0, nodes.len() - 1]);
+    assert_eq!(nodes[nodes.len() - 1].rect_tl, Vec2::zero());
+    assert_eq!(nodes[nodes.len() - 1].rect_dims, Vec2::from(10.0));
+
+    // grow several times in one push
+    assert!(tree.push(&Vec2::new(95.0, 12.0)));
+    assert_eq!(tree.border_top_left, Vec2::from(-10.0));
+    assert_eq!(tree.border_dims, Vec2::from(160.0));
+    assert_eq!(tree.len(), 11 * 11 + 2);
+
+    let queries = [
+        (Vec2::new(5.0, 5.0), 4.0),
+        (Vec2::new(-3.0, -7.5), 0.5),
+        (Vec2::new(90.0, 10.0), 8.0),
+        (Vec2::zero(), 1000.0),
+    ];
+    for (center, radius) in queries {
+        assert_eq!(
+            tree.query_circle(center, radius).len(),
+            tree.query_circle_brute_force(center, radius).len()
+        );
+    }
+    assert_eq!(
+        tree.nearest(Vec2::new(99.0, 9.0)),
+        Some(Vec2::new(95.0, 12.0))
+    );
+
+    // still rejects what can never fit
+    assert_eq!(
+        tree.try_push(&Vec2::new(f64::NAN, 0.0)),
+        Err(PushError::NotFinite)
+    );
+    let num_nodes = tree.get_nodes().len();
+    assert_eq!(
+        tree.try_push(&Vec2::new(f64::MAX, 0.0)),
+        Err(PushError::OutOfBounds)
+    );
+    // a failed push leaves the tree untouched
+    assert_eq!(tree.get_nodes().len(), num_nodes);
+    assert_eq!(tree.border_dims, Vec2::from(160.0));
+}
+
+#[test]
+fn test_quadtree_raycast() {
+    let mut tree = QuadTree::new(Vec2::zero(), Vec2::from(100.0));
+    assert!(tree
+        .raycast(Vec2::zero(), Vec2::new(1.0, 0.0), 100.0, 1.0)
+        .is_none());
+
+    tree.push(&Vec2::new(50.0, 10.0));
+    tree.push(&Vec2::new(30.0, 10.0));
+    tree.push(&Vec2::new(70.0, 10.5));
+    // straight along y = 10, hits the closest one
+    let hit = tree.raycast(Vec2::new(0.0, 10.0), Vec2::new(2.0, 0.0), 100.0, 1.0);
+    assert_eq!(hit, Some((Vec2::new(30.0, 10.0), 29.0)));
+    // from the other side
+    let hit = tree.raycast(Vec2::new(100.0, 10.0), Vec2::new(-1.0, 0.0), 100.0, 1.0);
+    assert_eq!(hit.unwrap().0, Vec2::new(70.0, 10.5));
+    // too short
+    assert!(tree
+        .raycast(Vec2::new(0.0, 10.0), Vec2::new(1.0, 0.0), 28.0, 1.0)
+        .is_none());
+    // pointing away
+    assert!(tree
+        .raycast(Vec2::new(0.0, 10.0), Vec2::new(-1.0, 0.0), 100.0, 1.0)
+        .is_none());
+    // starting inside an item
+    let hit = tree.raycast(Vec2::new(50.5, 10.0), Vec2::new(0.0, 1.0), 100.0, 1.0);
+    assert_eq!(hit, Some((Vec2::new(50.0, 10.0), 0.0)));
+    // zero direction
+    assert!(tree
+        .raycast(Vec2::new(0.0, 10.0), Vec2::zero(), 100.0, 1.0)
+        .is_none());
+    // the radius applies to every item, a wider one
+    // reaches an item beside the ray before the others
+    tree.push(&Vec2::new(20.0, 13.0));
+    let hit = tree.raycast(Vec2::new(0.0, 10.0), Vec2::new(1.0, 0.0), 100.0, 1.0);
+    assert_eq!(hit.unwrap().0, Vec2::new(30.0, 10.0));
+    let (item, dist) = tree
+        .raycast(Vec2::new(0.0, 10.0), Vec2::new(1.0, 0.0), 100.0, 3.5)
+        .unwrap();
+    assert_eq!(item, Vec2::new(20.0, 13.0));
+    assert!((dist - (20.0 - 3.25f64.sqrt())).abs() < 1e-9);
+
+    let mut tree = QuadTree::new(Vec2::zero(), Vec2::from(100.0));
+    for i in 0..=20 {
+        for j in 0..=20 {
+            tree.push(&Vec2::new(i as f64 * 5.0 + j as f64 * 0.1, j as f64 * 4.9));
+        }
+    }
+    let rays = [
+        (Vec2::new(-10.0, -10.0), Vec2::new(1.0, 1.0)),
+        (Vec2::new(51.0, 52.0), Vec2::new(0.3, -1.0)),
+        (Vec2::new(2.4, 50.0), Vec2::new(0.0, 1.0)),
+        (Vec2::new(120.0, 33.0), Vec2::new(-1.0, 0.01)),
+        (Vec2::new(-5.0, 200.0), Vec2::new(0.0, 1.0)),
+    ];
+    for (origin, dir) in rays {
+        for radius in [0.1, 1.0, 3.0] {
+            let hit = tree.raycast(origin, dir, 150.0, radius);
+            let expected = tree.raycast_brute_force(origin, dir, 150.0, radius);
+            assert_eq!(hit.map(|(_, dist)| dist), expected);
+        }
+    }
+}
+
+#[test]
+fn test_quadtree_morton_key() {
+    let tl = Vec2::zero();
+    let dims = Vec2::from(8.0);
+    assert_eq!(morton_key(Vec2::zero(), tl, dims), 0);
+    assert_eq!(morton_key(Vec2::from(8.0), tl, dims), u32::MAX);
+    // x in the even bits, y in the odd bits
+    assert_eq!(morton_key(Vec2::new(4.0, 0.0), tl, dims), 1 << 30);
+    assert_eq!(morton_key(Vec2::new(0.0, 4.0), tl, dims), 1 << 31);
+    // quadrants in order tl, tr, bl, br
+    let keys = [
+        Vec2::new(1.0, 3.0),
+        Vec2::new(7.0, 1.0),
+        Vec2::new(3.0, 5.0),
+        Vec2::new(5.0, 5.0),
+    ]
+    .map(|pos| morton_key(pos, tl, dims));
+    assert!(keys.windows(2).all(|w| w[0] <= w[1]));
+    // outside points are clamped
+    assert_eq!(morton_key(Vec2::from(-3.0), tl, dims), 0);
+}
+
+#[test]
+fn test_quadtree_build() {
+    // walks both trees side by side, node indices
+    // differ but every node must hold the same items
+    fn same_node(a: &QuadTree<Vec2>, a_idx: usize, b: &QuadTree<Vec2>, b_idx: usize) {
+        let (a_node, b_node) = (&a.nodes[a_idx], &b.nodes[b_idx]);
+        assert_eq!(a_node.rect_tl, b_node.rect_tl);
+        assert_eq!(a_node.rect_dims, b_node.rect_dims);
+        assert_eq!(a_node.items, b_node.items);
+        for (a_child, b_child) in a_node.children().into_iter().zip(b_node.children()) {
+            assert_eq!(a_child == 0, b_child == 0);
+            if a_child != 0 {
+                same_node(a, a_child, b, b_child);
+            }
+        }
+    }
+
+    let tl = Vec2::new(-5.0, 3.0);
+    let dims = Vec2::new(100.0, 60.0);
+    let mut points = Vec::new();
+    for i in 0..40 {
+        for j in 0..25 {
+            let x = (i * 37 % 40) as f64 * 2.5 + j as f64 * 0.07 - 5.0;
+            points.push(Vec2::new(x, (j * 11 % 25) as f64 * 2.4 + 3.0));
+        }
+    }
+    // coincident points stop at the depth limit
+    points.extend([Vec2::new(20.0, 20.0); 10]);
+    // on the edges and on a split line
+    points.extend([tl, tl + dims, tl + dims / 2.0]);
+
+    let mut morton = points.clone();
+    morton.sort_by_key(|pos| morton_key(*pos, tl, dims));
+    for mut items in [points, morton] {
+        let mut pushed = QuadTree::with_params(tl, dims, 3, 6);
+        for item in &items {
+            assert!(pushed.push(item));
+        }
+        let mut built = QuadTree::with_params(Vec2::zero(), Vec2::from(1.0), 3, 6);
+        built.push(&Vec2::from(0.5));
+        assert_eq!(built.build(tl, dims, &mut items), Ok(()));
+        assert_eq!(built.len(), pushed.len());
+        assert_eq!(built.border_top_left, tl);
+        assert_eq!(built.stats(), pushed.stats());
+        same_node(&built, 0, &pushed, 0);
+    }
+
+    // nothing changes when an item does not fit
+    let mut tree = QuadTree::new(tl, dims);
+    let mut items = vec![Vec2::from(10.0), Vec2::new(-6.0, 10.0)];
+    assert_eq!(
+        tree.build(tl, dims, &mut items),
+        Err(PushError::OutOfBounds)
+    );
+    items[1] = Vec2::new(f64::NAN, 10.0);
+    assert_eq!(tree.build(tl, dims, &mut items), Err(PushError::NotFinite));
+    assert_eq!(tree.len(), 0);
+    assert_eq!(tree.nodes.len(), 1);
+}
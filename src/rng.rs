@@ -0,0 +1,58 @@
+use crate::vec2::Vec2;
+
+/**
+xorshift64, small and seedable so a world
+replays the same way from the same seed
+ */
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift never leaves 0
+        Self(if seed == 0 { 0x5eed_b01d } else { seed })
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    /**
+    uniform in [0, 1)
+     */
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+    /**
+    uniform in [min, max)
+     */
+    pub fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + self.next_f64() * (max - min)
+    }
+    /**
+    uniform in the disc of the given radius around the origin
+     */
+    pub fn in_disc(&mut self, radius: f64) -> Vec2 {
+        let angle = self.range(0.0, std::f64::consts::TAU);
+        // sqrt so the points do not bunch up in the middle
+        Vec2::new(radius * self.next_f64().sqrt(), 0.0).rotate(angle)
+    }
+}
+
+#[test]
+fn test_rng() {
+    let mut a = Rng::new(42);
+    let mut b = Rng::new(42);
+    for _ in 0..100 {
+        assert_eq!(a.next_u64(), b.next_u64());
+    }
+    let mut zero = Rng::new(0);
+    assert_ne!(zero.next_u64(), 0);
+
+    let mut rng = Rng::new(7);
+    for _ in 0..1000 {
+        let x = rng.range(-2.0, 3.0);
+        assert!((-2.0..3.0).contains(&x));
+        assert!(rng.in_disc(5.0).length() <= 5.0 + 1e-12);
+    }
+}
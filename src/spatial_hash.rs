@@ -0,0 +1,220 @@
+use crate::{
+    spatial_index::SpatialIndex,
+    vec2::{Position, Vec2},
+};
+
+/**
+upper bound on cells per axis so a tiny cell size
+cannot allocate an enormous grid
+ */
+pub const MAX_CELLS_PER_AXIS: usize = 1024;
+
+/**
+uniform grid over a rectangle, each cell stores
+the items whose position falls inside it
+ */
+pub struct SpatialHash<T>
+where
+    T: Position + Copy,
+{
+    pub border_top_left: Vec2,
+    pub border_dims: Vec2,
+    // requested cell size, the actual cell dims are
+    // stretched so a whole number of cells fit the borders
+    cell_size: f64,
+    cell_dims: Vec2,
+    cols: usize,
+    rows: usize,
+    // row major, cells are cleared instead of
+    // dropped on reset to keep their allocations
+    cells: Vec<Vec<T>>,
+    num_items: usize,
+}
+impl<T> SpatialHash<T>
+where
+    T: Position + Copy,
+{
+    pub fn new(border_top_left: Vec2, border_dims: Vec2, cell_size: f64) -> Self {
+        let mut ret = Self {
+            border_top_left,
+            border_dims,
+            cell_size,
+            cell_dims: Vec2::zero(),
+            cols: 0,
+            rows: 0,
+            cells: Vec::new(),
+            num_items: 0,
+        };
+        ret.reset(border_top_left, border_dims);
+        ret
+    }
+    /**
+    takes effect on the next reset
+     */
+    pub fn set_cell_size(&mut self, cell_size: f64) {
+        self.cell_size = cell_size;
+    }
+    /**
+    column and row of the cell containing pos,
+    clamped to the grid
+     */
+    fn cell_coords(&self, pos: Vec2) -> (usize, usize) {
+        let rel = pos - self.border_top_left;
+        let col = (rel.x / self.cell_dims.x).floor();
+        let row = (rel.y / self.cell_dims.y).floor();
+        // float to int casts saturate, so negatives become 0
+        (
+            (col as usize).min(self.cols - 1),
+            (row as usize).min(self.rows - 1),
+        )
+    }
+    #[cfg(test)]
+    fn query_circle_brute_force(&self, center: Vec2, radius: f64) -> Vec<T> {
+        let mut ret = Vec::new();
+        for cell in &self.cells {
+            for item in cell {
+                if item.pos().distance(&center) <= radius {
+                    ret.push(*item);
+                }
+            }
+        }
+        ret
+    }
+}
+impl<T> SpatialIndex<T> for SpatialHash<T>
+where
+    T: Position + Copy,
+{
+    fn len(&self) -> usize {
+        self.num_items
+    }
+    fn reset(&mut self, border_top_left: Vec2, border_dims: Vec2) {
+        let cells_along = |len: f64| {
+            let cells = (len / self.cell_size).ceil();
+            if cells.is_finite() && cells >= 1.0 {
+                (cells as usize).min(MAX_CELLS_PER_AXIS)
+            } else {
+                1
+            }
+        };
+        self.cols = cells_along(border_dims.x);
+        self.rows = cells_along(border_dims.y);
+        self.cell_dims = Vec2::new(
+            border_dims.x / self.cols as f64,
+            border_dims.y / self.rows as f64,
+        );
+        self.border_top_left = border_top_left;
+        self.border_dims = border_dims;
+
+        for cell in &mut self.cells {
+            cell.clear();
+        }
+        self.cells.resize_with(self.cols * self.rows, Vec::new);
+        self.num_items = 0;
+    }
+    fn push(&mut self, item: &T) -> bool {
+        if item.x() < self.border_top_left.x
+            || item.x() > self.border_top_left.x + self.border_dims.x
+            || item.y() < self.border_top_left.y
+            || item.y() > self.border_top_left.y + self.border_dims.y
+        {
+            return false;
+        }
+
+        let (col, row) = self.cell_coords(item.pos());
+        self.cells[row * self.cols + col].push(*item);
+        self.num_items += 1;
+        true
+    }
+    fn query_circle_with<F>(&self, center: Vec2, radius: f64, _stack: &mut Vec<usize>, mut f: F)
+    where
+        F: FnMut(&T),
+    {
+        if self.num_items == 0
+            || center.x + radius < self.border_top_left.x
+            || center.x - radius > self.border_top_left.x + self.border_dims.x
+            || center.y + radius < self.border_top_left.y
+            || center.y - radius > self.border_top_left.y + self.border_dims.y
+        {
+            return;
+        }
+
+        let radius_sq = radius * radius;
+        let (min_col, min_row) = self.cell_coords(center - radius);
+        let (max_col, max_row) = self.cell_coords(center + radius);
+        for row in min_row..=max_row {
+            for cell in &self.cells[row * self.cols + min_col..=row * self.cols + max_col] {
+                for item in cell {
+                    if item.pos().distance_squared(&center) <= radius_sq {
+                        f(item);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_spatial_hash_dims() {
+    let hash = SpatialHash::<Vec2>::new(Vec2::zero(), Vec2::new(100.0, 35.0), 10.0);
+    assert_eq!(hash.cols, 10);
+    assert_eq!(hash.rows, 4);
+    assert_eq!(hash.cell_dims, Vec2::new(10.0, 8.75));
+    assert_eq!(hash.cells.len(), 40);
+
+    // degenerate cell sizes fall back to a bounded grid
+    let hash = SpatialHash::<Vec2>::new(Vec2::zero(), Vec2::from(100.0), 0.0);
+    assert_eq!(hash.cols, 1);
+    let hash = SpatialHash::<Vec2>::new(Vec2::zero(), Vec2::from(100.0), 1e-9);
+    assert_eq!(hash.cols, MAX_CELLS_PER_AXIS);
+}
+
+#[test]
+fn test_spatial_hash_push() {
+    let mut hash = SpatialHash::new(Vec2::zero(), Vec2::from(10.0), 3.0);
+    for i in 0..=10 {
+        for j in 0..=10 {
+            assert!(hash.push(&Vec2::new(i as f64, j as f64)));
+        }
+    }
+    assert_eq!(hash.len(), 121);
+    assert!(!hash.push(&Vec2::from(-1.0)));
+    assert!(!hash.push(&Vec2::new(7.0, 10.3)));
+    assert_eq!(hash.len(), 121);
+
+    hash.reset(Vec2::from(5.0), Vec2::from(20.0));
+    assert_eq!(hash.len(), 0);
+    let mut buf = Vec::new();
+    hash.query_circle_with(Vec2::from(5.0), 100.0, &mut Vec::new(), |item| {
+        buf.push(*item)
+    });
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn test_spatial_hash_query_circle() {
+    let mut hash = SpatialHash::new(Vec2::from(-3.0), Vec2::new(107.0, 53.0), 7.5);
+    for i in 0..=100 {
+        for j in 0..=50 {
+            hash.push(&Vec2::new(i as f64 + j as f64 * 0.01, j as f64));
+        }
+    }
+    let queries = [
+        (Vec2::new(50.0, 25.0), 7.5),
+        (Vec2::new(50.0, 25.0), 0.0),
+        (Vec2::zero(), 3.5),
+        (Vec2::new(99.9, 1.2), 30.0),
+        (Vec2::new(-20.0, 25.0), 21.0),
+        (Vec2::new(250.0, 25.0), 10.0),
+        (Vec2::new(50.0, 25.0), 1000.0),
+    ];
+    for (center, radius) in queries {
+        let mut got = Vec::new();
+        hash.query_circle_with(center, radius, &mut Vec::new(), |item| got.push(*item));
+        let mut expected = hash.query_circle_brute_force(center, radius);
+        let by_pos = |a: &Vec2, b: &Vec2| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y));
+        got.sort_by(by_pos);
+        expected.sort_by(by_pos);
+        assert_eq!(got, expected);
+    }
+}
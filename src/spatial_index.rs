@@ -0,0 +1,28 @@
+use crate::vec2::{Position, Vec2};
+
+/**
+common interface of the spatial acceleration
+structures used to find neighbouring boids
+ */
+pub trait SpatialIndex<T>
+where
+    T: Position + Copy,
+{
+    fn len(&self) -> usize;
+    /**
+    removes every item and sets new borders
+     */
+    fn reset(&mut self, border_top_left: Vec2, border_dims: Vec2);
+    /**
+    returns false if the item is outside the borders
+     */
+    fn push(&mut self, item: &T) -> bool;
+    /**
+    calls f on every item inside the circle. stack is scratch
+    space for indexes that walk a tree, kept by the caller
+    so that queries do not allocate
+     */
+    fn query_circle_with<F>(&self, center: Vec2, radius: f64, stack: &mut Vec<usize>, f: F)
+    where
+        F: FnMut(&T);
+}
@@ -23,6 +23,7 @@ impl Vec2 {
     pub fn rand_01() -> Self {
         Self::new(js_sys::Math::random(), js_sys::Math::random())
     }
+    #[allow(clippy::wrong_self_convention)]
     pub fn to_str(&self) -> String {
         format!("Vec2({:.2}, {:.2})", self.x, self.y)
     }
@@ -39,7 +40,13 @@ impl Vec2 {
     pub fn keep_y(&self) -> Self {
         Self::new(0.0, self.y)
     }
+    pub fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite()
+    }
 
+    pub fn dot(&self, other: &Vec2) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
     pub fn length_squared(&self) -> f64 {
         self.x * self.x + self.y * self.y
     }
@@ -66,7 +73,10 @@ impl Vec2 {
     }
     pub fn clamp_length(&self, min: f64, max: f64) -> Self {
         let len = self.length();
-        if len < min {
+        if len == 0.0 {
+            // no direction to scale along
+            *self
+        } else if len < min {
             self.normalize() * min
         } else if len > max {
             self.normalize() * max
@@ -201,3 +211,20 @@ fn test_vec2_eq() {
     assert_ne!(Vec2::from(25.4), Vec2::from(50.80000000001) / 2.0);
     assert_ne!(Vec2::new(9.4, 5.6), Vec2::new(12.6, 5.7));
 }
+
+#[test]
+fn test_vec2_clamp_length() {
+    assert_eq!(Vec2::zero().clamp_length(1.0, 2.0), Vec2::zero());
+    assert_eq!(
+        Vec2::new(0.3, 0.4).clamp_length(1.0, 2.0),
+        Vec2::new(0.6, 0.8)
+    );
+    assert_eq!(
+        Vec2::new(3.0, 4.0).clamp_length(1.0, 2.0),
+        Vec2::new(1.2, 1.6)
+    );
+    assert_eq!(
+        Vec2::new(1.2, 0.0).clamp_length(1.0, 2.0),
+        Vec2::new(1.2, 0.0)
+    );
+}
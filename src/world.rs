@@ -1,7 +1,19 @@
 use wasm_bindgen::prelude::wasm_bindgen;
 
 use crate::{
-    quadtree::QuadTree,
+    attractor::{Attractor, Falloff},
+    energy::{EnergyStats, Food},
+    flock::Flock,
+    flow_field::FlowField,
+    leader::Leader,
+    loose_quadtree::{Bounded, LooseQuadTree},
+    neighbours::{NeighbourSums, Neighbours, SeparationFalloff},
+    params::{BoidParam, BoidParams, PARAMS},
+    path::Path,
+    quadtree::{morton_key, QuadTree, QuadTreeStats},
+    rng::Rng,
+    spatial_hash::SpatialHash,
+    spatial_index::SpatialIndex,
     vec2::{Position, Vec2},
 };
 
@@ -23,11 +35,176 @@ impl Position for Boid {
     }
 }
 
+/**
+below this many boids, spreading a tick over
+threads costs more than it saves
+ */
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN_BOIDS: usize = 1024;
+
+/**
+boid as a circle in the collision tree
+ */
+#[derive(Clone, Copy)]
+struct BoidBody {
+    idx: usize,
+    pos: Vec2,
+    radius: f64,
+}
+impl Position for BoidBody {
+    fn pos(&self) -> Vec2 {
+        self.pos
+    }
+}
+impl Bounded for BoidBody {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+}
+
+/**
+spatial index used to find neighbours in tick
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpatialBackend {
+    QuadTree,
+    SpatialHash,
+}
+
+/**
+what holding the pointer down does
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointerMode {
+    Attract,
+    Repel,
+    // spawns boids around the pointer
+    Paint,
+    // removes boids around the pointer
+    Erase,
+}
+
+/**
+the wander circle is this many radii ahead of the boid
+ */
+const WANDER_DISTANCE: f64 = 2.0;
+
+/**
+slot of an id whose boid was removed
+ */
+const REMOVED: usize = usize::MAX;
+
 #[wasm_bindgen]
 pub struct World {
     pub dims: Vec2,
-    boids: Vec<Boid>,
+    boids: Flock,
+    // slot of each boid in boids by id, or REMOVED
+    slots: Vec<usize>,
+    backend: SpatialBackend,
+    // only the index of the current backend is kept up to date
     quadtree: QuadTree<Boid>,
+    spatial_hash: SpatialHash<Boid>,
+    // boids were removed since the index was built, it is
+    // rebuilt once before it is next queried
+    index_dirty: bool,
+    // morton key and slot of every boid, and the boids in
+    // that order, reused for each build of the quadtree
+    morton_order: Vec<(u32, usize)>,
+    sorted_boids: Vec<Boid>,
+    // reused between ticks to avoid allocating per boid
+    neighbours: Neighbours,
+    // double buffer for the parallel update
+    #[cfg(feature = "parallel")]
+    next_boids: Flock,
+    collision_tree: LooseQuadTree<BoidBody>,
+    corrections: Vec<Vec2>,
+    // indexed by handle, removed attractors leave
+    // a None so the other handles stay valid
+    attractors: Vec<Option<Attractor>>,
+    pointer: Option<(Vec2, PointerMode)>,
+    // added to every boid's velocity each tick
+    pub wind: Vec2,
+    // currents that vary over the world
+    flow_field: Option<FlowField>,
+    // radius of every pointer mode
+    pub pointer_radius: f64,
+    // steering at the pointer in attract and repel modes
+    pub pointer_strength: f64,
+    // boids spawned per tick in paint mode
+    pub paint_rate: usize,
+    // steering towards a point that drifts
+    // around a circle ahead of each boid
+    pub wander_strength: f64,
+    // most the wander point moves per tick, in radians
+    pub wander_rate: f64,
+    // width in radians of the uniform random turn
+    // every boid takes each tick
+    pub noise: f64,
+    // random turn of each boid this tick, by slot
+    noise_angles: Vec<f64>,
+    leaders: Vec<Leader>,
+    // weight of seeking the point behind a leader,
+    // and of leaders seeking their waypoints
+    pub follow_factor: f64,
+    // how far behind their leader followers stay
+    pub follow_distance: f64,
+    // a leader moves on to its next waypoint this close to it
+    pub waypoint_radius: f64,
+    path: Option<Path>,
+    // weight of steering back onto the path
+    pub path_factor: f64,
+    // how many ticks ahead boids predict
+    // whether they are leaving the path
+    pub path_lookahead: f64,
+    // longest the combined steering of a boid can be per tick,
+    // infinite by default so any rule can turn a boid at once
+    pub max_force: f64,
+    // indexed by handle like attractors
+    foods: Vec<Option<Food>>,
+    // energy of a new boid, and the most a boid can have
+    pub max_energy: f64,
+    // energy burned per tick for each unit of speed,
+    // boids never starve at 0
+    pub metabolism: f64,
+    // weight of seeking the closest food, scaled by
+    // how much energy a boid is missing
+    pub food_factor: f64,
+    starved: usize,
+    // ticks a boid lives for, 0 lives forever
+    pub max_age: usize,
+    // chance per tick that a boid able to
+    // have a child has one
+    pub birth_rate: f64,
+    // age a boid can first have children at
+    pub maturity: usize,
+    // energy a parent gives up for a child, it needs more
+    // than this to have one. children start with max_energy
+    pub birth_cost: f64,
+    // each parameter of a child is the parent's scaled
+    // by a random factor within mutation of 1
+    pub mutation: f64,
+    // no children are born once there are this many boids
+    pub max_population: usize,
+    births: usize,
+    deaths: usize,
+    // when set, max_force is handed out to the rules in order of
+    // priority, margins first, then separation, alignment, cohesion
+    // and the goals, instead of truncating their sum
+    pub prioritized: bool,
+    rng: Rng,
+    ticks: usize,
+    // every this many ticks the boids are sorted along the
+    // z-order curve, so boids close in space are close in
+    // memory. 0 never sorts
+    pub reorder_interval: usize,
+    // when set, overlapping boids are pushed
+    // apart at the end of every tick
+    pub collisions: bool,
+    pub boid_radius: f64,
+    pub separation_falloff: SeparationFalloff,
+    // defaults for the parameters a boid can override
     pub protect_range: f64,
     pub visible_range: f64,
     pub avoid_factor: f64,
@@ -40,6 +217,7 @@ pub struct World {
 }
 #[wasm_bindgen]
 impl World {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         dims: &Vec2,
         visible_range: f64,
@@ -52,10 +230,68 @@ impl World {
         max_speed: f64,
         min_speed: f64,
     ) -> Self {
+        // boids added between ticks can be outside the
+        // bounding box the quadtree was last built with
+        let mut quadtree = QuadTree::new(Vec2::zero(), *dims);
+        quadtree.set_auto_expand(true);
         World {
             dims: *dims,
-            boids: Vec::new(),
-            quadtree: QuadTree::new(Vec2::zero(), *dims),
+            boids: Flock::default(),
+            slots: Vec::new(),
+            backend: SpatialBackend::QuadTree,
+            quadtree,
+            spatial_hash: SpatialHash::new(
+                Vec2::zero(),
+                *dims,
+                f64::max(visible_range, protect_range),
+            ),
+            index_dirty: false,
+            morton_order: Vec::new(),
+            sorted_boids: Vec::new(),
+            neighbours: Neighbours::default(),
+            #[cfg(feature = "parallel")]
+            next_boids: Flock::default(),
+            collision_tree: LooseQuadTree::new(Vec2::zero(), *dims),
+            corrections: Vec::new(),
+            attractors: Vec::new(),
+            pointer: None,
+            wind: Vec2::zero(),
+            flow_field: None,
+            pointer_radius: 50.0,
+            pointer_strength: 1.0,
+            paint_rate: 4,
+            wander_strength: 0.0,
+            wander_rate: 0.3,
+            noise: 0.0,
+            noise_angles: Vec::new(),
+            leaders: Vec::new(),
+            follow_factor: 0.05,
+            follow_distance: 20.0,
+            waypoint_radius: 20.0,
+            path: None,
+            path_factor: 0.1,
+            path_lookahead: 10.0,
+            max_force: f64::INFINITY,
+            prioritized: false,
+            foods: Vec::new(),
+            max_energy: 100.0,
+            metabolism: 0.0,
+            food_factor: 0.5,
+            starved: 0,
+            max_age: 0,
+            birth_rate: 0.0,
+            maturity: 100,
+            birth_cost: 0.0,
+            mutation: 0.05,
+            max_population: 10_000,
+            births: 0,
+            deaths: 0,
+            rng: Rng::new(0),
+            ticks: 0,
+            reorder_interval: 0,
+            collisions: false,
+            boid_radius: 0.0,
+            separation_falloff: SeparationFalloff::Linear,
             protect_range,
             avoid_factor,
             visible_range,
@@ -67,100 +303,1043 @@ impl World {
             min_speed,
         }
     }
-    pub fn add_boid(&mut self, pos: &Vec2, vel: &Vec2) {
-        self.boids.push(Boid::new(*pos, *vel));
-        self.quadtree.push(&Boid::new(*pos, *vel));
-        debug_assert_eq!(self.boids.len(), self.quadtree.len());
+    /**
+    returns the id of the new boid, ids count up from 0
+    and do not change when the boids are reordered
+     */
+    pub fn add_boid(&mut self, pos: &Vec2, vel: &Vec2) -> usize {
+        let boid = Boid::new(*pos, *vel);
+        let id = self.spawn(boid);
+        if self.index_dirty {
+            // the rebuild will pick it up
+            return id;
+        }
+        if !boid.pos.is_finite() {
+            // no index can place it, it is left out
+            return id;
+        }
+        match self.backend {
+            SpatialBackend::QuadTree => {
+                let success = self.quadtree.push(&boid);
+                debug_assert!(success);
+            }
+            SpatialBackend::SpatialHash => {
+                if !self.spatial_hash.push(&boid) {
+                    // the grid cannot grow in place, rebuild
+                    // it over an area that includes the boid
+                    let tl = Vec2::new(
+                        f64::min(self.spatial_hash.border_top_left.x, boid.pos.x),
+                        f64::min(self.spatial_hash.border_top_left.y, boid.pos.y),
+                    );
+                    let old_br = self.spatial_hash.border_top_left + self.spatial_hash.border_dims;
+                    let br = Vec2::new(
+                        f64::max(old_br.x, boid.pos.x),
+                        f64::max(old_br.y, boid.pos.y),
+                    );
+                    self.rebuild_index(tl, br - tl);
+                }
+            }
+        };
+        debug_assert_eq!(self.num_indexable(), self.index_len());
+        id
+    }
+    /**
+    returns false if the boid was already removed or never existed,
+    the ids of the other boids do not change
+     */
+    pub fn remove_boid(&mut self, id: usize) -> bool {
+        if !self.remove_from_flock(id) {
+            return false;
+        }
+        self.index_dirty = true;
+        true
+    }
+    pub fn has_boid(&self, id: usize) -> bool {
+        self.slots.get(id).is_some_and(|&slot| slot != REMOVED)
     }
     pub fn num_boids(&self) -> usize {
-        debug_assert_eq!(self.boids.len(), self.quadtree.len());
+        debug_assert!(self.index_dirty || self.num_indexable() == self.index_len());
         self.boids.len()
     }
-    pub fn get_boid(&self, i: usize) -> Boid {
-        // * what if i >= seld.boids.len()?
-        self.boids[i]
+    pub fn backend(&self) -> SpatialBackend {
+        self.backend
+    }
+    /**
+    switches the spatial index and rebuilds it
+    over the same area as the previous one
+     */
+    pub fn set_backend(&mut self, backend: SpatialBackend) {
+        if backend == self.backend {
+            return;
+        }
+        let (border_top_left, border_dims) = match self.backend {
+            SpatialBackend::QuadTree => (self.quadtree.border_top_left, self.quadtree.border_dims),
+            SpatialBackend::SpatialHash => (
+                self.spatial_hash.border_top_left,
+                self.spatial_hash.border_dims,
+            ),
+        };
+        self.backend = backend;
+        self.rebuild_index(border_top_left, border_dims);
+    }
+    pub fn quadtree_max_points(&self) -> usize {
+        self.quadtree.max_points()
+    }
+    pub fn quadtree_max_depth(&self) -> usize {
+        self.quadtree.max_depth()
+    }
+    /**
+    None when the quadtree is not the current backend
+     */
+    pub fn quadtree_stats(&mut self) -> Option<QuadTreeStats> {
+        self.refresh_index();
+        match self.backend {
+            SpatialBackend::QuadTree => Some(self.quadtree.stats()),
+            SpatialBackend::SpatialHash => None,
+        }
+    }
+    /**
+    rebuilds the quadtree with the new node capacity and depth limit
+     */
+    pub fn set_quadtree_params(&mut self, max_points: usize, max_depth: usize) {
+        self.quadtree = QuadTree::with_params(
+            self.quadtree.border_top_left,
+            self.quadtree.border_dims,
+            max_points,
+            max_depth,
+        );
+        self.quadtree.set_auto_expand(true);
+        if self.backend == SpatialBackend::QuadTree {
+            self.rebuild_index(self.quadtree.border_top_left, self.quadtree.border_dims);
+        }
+    }
+    pub fn get_boid(&self, id: usize) -> Boid {
+        // * what if !self.has_boid(id)?
+        self.boids.get(self.slots[id])
+    }
+    /**
+    every boid as pos.x, pos.y, vel.x, vel.y in a flat array
+    ordered by id, cheap to copy out of a web worker
+     */
+    pub fn boid_data(&self) -> Vec<f64> {
+        let mut ret = Vec::with_capacity(self.boids.len() * 4);
+        for &i in self.slots.iter().filter(|&&slot| slot != REMOVED) {
+            ret.extend_from_slice(&[
+                self.boids.xs[i],
+                self.boids.ys[i],
+                self.boids.vxs[i],
+                self.boids.vys[i],
+            ]);
+        }
+        ret
+    }
+    /**
+    returns a handle for moving or removing the attractor,
+    a negative strength repels boids instead
+     */
+    pub fn add_attractor(
+        &mut self,
+        pos: &Vec2,
+        strength: f64,
+        radius: f64,
+        falloff: Falloff,
+    ) -> usize {
+        self.attractors
+            .push(Some(Attractor::new(*pos, strength, radius, falloff)));
+        self.attractors.len() - 1
+    }
+    /**
+    returns false if the handle was removed or never existed
+     */
+    pub fn update_attractor(
+        &mut self,
+        handle: usize,
+        pos: &Vec2,
+        strength: f64,
+        radius: f64,
+        falloff: Falloff,
+    ) -> bool {
+        match self.attractors.get_mut(handle) {
+            Some(Some(attractor)) => {
+                *attractor = Attractor::new(*pos, strength, radius, falloff);
+                true
+            }
+            _ => false,
+        }
+    }
+    /**
+    returns false if the handle was removed or never existed
+     */
+    pub fn move_attractor(&mut self, handle: usize, pos: &Vec2) -> bool {
+        match self.attractors.get_mut(handle) {
+            Some(Some(attractor)) => {
+                attractor.pos = *pos;
+                true
+            }
+            _ => false,
+        }
+    }
+    /**
+    returns false if the handle was already removed or never existed
+     */
+    pub fn remove_attractor(&mut self, handle: usize) -> bool {
+        match self.attractors.get_mut(handle) {
+            Some(attractor) => attractor.take().is_some(),
+            None => false,
+        }
+    }
+    pub fn num_attractors(&self) -> usize {
+        self.attractors.iter().flatten().count()
+    }
+    /**
+    returns a handle for removing the food, boids within
+    radius of pos gain rate energy every tick
+     */
+    pub fn add_food(&mut self, pos: &Vec2, radius: f64, rate: f64) -> usize {
+        self.foods.push(Some(Food::new(*pos, radius, rate)));
+        self.foods.len() - 1
+    }
+    /**
+    returns false if the handle was removed or never existed
+     */
+    pub fn remove_food(&mut self, handle: usize) -> bool {
+        match self.foods.get_mut(handle) {
+            Some(food) => food.take().is_some(),
+            None => false,
+        }
+    }
+    pub fn num_foods(&self) -> usize {
+        self.foods.iter().flatten().count()
+    }
+    /**
+    returns false if there is no boid with the id, energy
+    above max_energy is capped
+     */
+    pub fn set_boid_energy(&mut self, id: usize, energy: f64) -> bool {
+        if !self.has_boid(id) {
+            return false;
+        }
+        self.boids.energies[self.slots[id]] = energy.min(self.max_energy);
+        true
+    }
+    /**
+    none if there is no boid with the id
+     */
+    pub fn boid_energy(&self, id: usize) -> Option<f64> {
+        self.has_boid(id)
+            .then(|| self.boids.energies[self.slots[id]])
+    }
+    pub fn energy_stats(&self) -> EnergyStats {
+        EnergyStats::new(&self.boids.energies, self.starved)
+    }
+    /**
+    ticks since the boid was added, none if there
+    is no boid with the id
+     */
+    pub fn boid_age(&self, id: usize) -> Option<usize> {
+        self.has_boid(id).then(|| self.boids.ages[self.slots[id]])
+    }
+    /**
+    children born in the last tick
+     */
+    pub fn births(&self) -> usize {
+        self.births
+    }
+    /**
+    boids that starved or died of old age in the last tick
+     */
+    pub fn deaths(&self) -> usize {
+        self.deaths
+    }
+    /**
+    the pointer acts on every tick until it is cleared
+     */
+    pub fn set_pointer(&mut self, pos: &Vec2, mode: PointerMode) {
+        self.pointer = Some((*pos, mode));
+    }
+    pub fn clear_pointer(&mut self) {
+        self.pointer = None;
+    }
+    /**
+    boids within visible_range of a leader follow it.
+    returns false if there is no boid with the id
+     */
+    pub fn set_leader(&mut self, id: usize, leader: bool) -> bool {
+        if !self.has_boid(id) {
+            return false;
+        }
+        self.boids.leaders[self.slots[id]] = leader;
+        let pos = self.leaders.iter().position(|l| l.id == id);
+        match (pos, leader) {
+            (None, true) => self.leaders.push(Leader::new(id, self.get_boid(id))),
+            (Some(pos), false) => {
+                self.leaders.remove(pos);
+            }
+            _ => {}
+        }
+        true
+    }
+    pub fn is_leader(&self, id: usize) -> bool {
+        self.has_boid(id) && self.boids.leaders[self.slots[id]]
+    }
+    pub fn leader_ids(&self) -> Vec<usize> {
+        self.leaders.iter().map(|l| l.id).collect()
+    }
+    /**
+    the leader steers through the waypoints, given as x, y pairs,
+    in order and starts over when looped. an empty list clears
+    the path. returns false if the boid is not a leader
+     */
+    pub fn set_leader_path(&mut self, id: usize, waypoints: &[f64], looped: bool) -> bool {
+        if waypoints.len() % 2 != 0 {
+            return false;
+        }
+        match self.leaders.iter_mut().find(|l| l.id == id) {
+            Some(leader) => {
+                let waypoints = waypoints
+                    .chunks_exact(2)
+                    .map(|p| Vec2::new(p[0], p[1]))
+                    .collect();
+                leader.set_path(waypoints, looped);
+                true
+            }
+            None => false,
+        }
+    }
+    /**
+    every boid follows the path through the points, given as x, y
+    pairs, staying within radius of it. returns false with fewer
+    than 2 points
+     */
+    pub fn set_path(&mut self, points: &[f64], closed: bool, radius: f64) -> bool {
+        if points.len() % 2 != 0 {
+            return false;
+        }
+        let points = points
+            .chunks_exact(2)
+            .map(|p| Vec2::new(p[0], p[1]))
+            .collect();
+        match Path::new(points, closed, radius) {
+            Some(path) => {
+                self.path = Some(path);
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn clear_path(&mut self) {
+        self.path = None;
+    }
+    /**
+    returns false if there is no boid with the id, or
+    the mass is not a positive number
+     */
+    pub fn set_boid_mass(&mut self, id: usize, mass: f64) -> bool {
+        if !(self.has_boid(id) && mass > 0.0 && mass.is_finite()) {
+            return false;
+        }
+        self.boids.masses[self.slots[id]] = mass;
+        true
+    }
+    /**
+    none if there is no boid with the id
+     */
+    pub fn boid_mass(&self, id: usize) -> Option<f64> {
+        self.has_boid(id).then(|| self.boids.masses[self.slots[id]])
+    }
+    /**
+    gives the boid its own value of param, instead of the
+    world's. returns false if there is no boid with the id,
+    the value is not a finite number, a speed or range is
+    negative, or the min speed would be above the max speed
+     */
+    pub fn set_boid_param(&mut self, id: usize, param: BoidParam, value: f64) -> bool {
+        if !(self.has_boid(id) && value.is_finite()) {
+            return false;
+        }
+        if param.is_magnitude() && value < 0.0 {
+            return false;
+        }
+        let params = self.params(self.slots[id]);
+        let crossed = match param {
+            BoidParam::MinSpeed => value > params.max_speed,
+            BoidParam::MaxSpeed => value < params.min_speed,
+            _ => false,
+        };
+        if crossed {
+            return false;
+        }
+        self.boids.overrides[self.slots[id]]
+            .get_or_insert_with(Box::default)
+            .set(param, Some(value));
+        true
+    }
+    /**
+    the boid goes back to the world's value of param.
+    returns false if there is no boid with the id
+     */
+    pub fn clear_boid_param(&mut self, id: usize, param: BoidParam) -> bool {
+        if !self.has_boid(id) {
+            return false;
+        }
+        let overrides = &mut self.boids.overrides[self.slots[id]];
+        if let Some(o) = overrides {
+            o.set(param, None);
+            if o.is_empty() {
+                *overrides = None;
+            }
+        }
+        true
+    }
+    /**
+    value of param the boid uses, its own or the world's.
+    none if there is no boid with the id
+     */
+    pub fn boid_param(&self, id: usize, param: BoidParam) -> Option<f64> {
+        self.has_boid(id)
+            .then(|| self.params(self.slots[id]).get(param))
+    }
+    /**
+    restarts the random numbers behind painting, wander and
+    noise, the same seed and inputs replay the same way
+     */
+    pub fn set_seed(&mut self, seed: u32) {
+        self.rng = Rng::new(seed as u64);
+    }
+    /**
+    replaces the flow field with a grid of cols by rows vectors
+    spread evenly over the world, given as x, y pairs in row
+    major order. returns false if the grid is smaller than 2x2
+    or data has the wrong length
+     */
+    pub fn set_flow_field(&mut self, cols: usize, rows: usize, data: &[f64]) -> bool {
+        match FlowField::from_slice(Vec2::zero(), self.dims, cols, rows, data) {
+            Some(field) => {
+                self.flow_field = Some(field);
+                true
+            }
+            None => false,
+        }
+    }
+    /**
+    replaces the flow field with curl noise, see set_flow_field for
+    the grid. scale is the noise frequency per pixel, and the
+    strongest current has length strength
+     */
+    pub fn set_curl_noise_field(
+        &mut self,
+        cols: usize,
+        rows: usize,
+        scale: f64,
+        strength: f64,
+        seed: u32,
+    ) -> bool {
+        let field = FlowField::curl_noise(
+            Vec2::zero(),
+            self.dims,
+            cols,
+            rows,
+            scale,
+            strength,
+            seed as u64,
+        );
+        match field {
+            Some(field) => {
+                self.flow_field = Some(field);
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn clear_flow_field(&mut self) {
+        self.flow_field = None;
+    }
+    /**
+    0 when there is no flow field
+     */
+    pub fn flow_field_cols(&self) -> usize {
+        self.flow_field.as_ref().map_or(0, FlowField::cols)
+    }
+    /**
+    0 when there is no flow field
+     */
+    pub fn flow_field_rows(&self) -> usize {
+        self.flow_field.as_ref().map_or(0, FlowField::rows)
+    }
+    /**
+    the flow field vectors in the layout set_flow_field takes
+     */
+    pub fn flow_field_data(&self) -> Vec<f64> {
+        self.flow_field.as_ref().map_or(Vec::new(), FlowField::data)
+    }
+    /**
+    rebuilds the spatial index around the current boids,
+    tick already does this at the end of every frame
+     */
+    pub(crate) fn rebuild(&mut self) {
+        let (tl, dims) = self.boid_bounds();
+        self.rebuild_index(tl, dims);
+    }
+    /**
+    rebuilds the spatial index if boids were
+    removed since it was last built
+     */
+    fn refresh_index(&mut self) {
+        if self.index_dirty {
+            self.rebuild();
+        }
+    }
+
+    /**
+    adds a boid to the flock but not to the spatial index,
+    returns its id
+     */
+    fn spawn(&mut self, boid: Boid) -> usize {
+        let id = self.slots.len();
+        self.slots.push(self.boids.len());
+        self.boids.push(id, boid);
+        self.boids.energies[self.slots[id]] = self.max_energy;
+        id
+    }
+    /**
+    removes the boid from the flock but
+    not from the spatial index
+     */
+    fn remove_from_flock(&mut self, id: usize) -> bool {
+        if !self.has_boid(id) {
+            return false;
+        }
+        let slot = self.slots[id];
+        self.boids.swap_remove(slot);
+        if slot < self.boids.len() {
+            // the last boid moved into the gap
+            self.slots[self.boids.ids[slot]] = slot;
+        }
+        self.slots[id] = REMOVED;
+        self.leaders.retain(|l| l.id != id);
+        true
+    }
+    /**
+    spawns or erases boids in paint and erase mode,
+    attract and repel are steering terms instead
+     */
+    fn apply_pointer(&mut self) {
+        let Some((pos, mode)) = self.pointer else {
+            return;
+        };
+        match mode {
+            PointerMode::Attract | PointerMode::Repel => {}
+            PointerMode::Paint => {
+                for _ in 0..self.paint_rate {
+                    let boid_pos = pos + self.rng.in_disc(self.pointer_radius);
+                    let speed = self.rng.range(self.min_speed, self.max_speed);
+                    let angle = self.rng.range(0.0, std::f64::consts::TAU);
+                    let vel = Vec2::new(speed, 0.0).rotate(angle);
+                    self.add_boid(&boid_pos, &vel);
+                }
+            }
+            PointerMode::Erase => {
+                let radius_sq = self.pointer_radius * self.pointer_radius;
+                let erased: Vec<usize> = (0..self.boids.len())
+                    .filter(|&i| self.boids.pos(i).distance_squared(&pos) <= radius_sq)
+                    .map(|i| self.boids.ids[i])
+                    .collect();
+                for &id in &erased {
+                    self.remove_from_flock(id);
+                }
+                if !erased.is_empty() {
+                    self.index_dirty = true;
+                }
+            }
+        }
+    }
+    /**
+    moves the wander points and picks the noise turns for
+    this tick. drawn in id order, so the results do not depend
+    on how the flock is sorted or split over threads
+     */
+    fn draw_randomness(&mut self) {
+        let wander = self.wander_strength != 0.0;
+        let noise = self.noise != 0.0;
+        self.noise_angles.clear();
+        if noise {
+            self.noise_angles.resize(self.boids.len(), 0.0);
+        }
+        if !wander && !noise {
+            return;
+        }
+        for &slot in self.slots.iter().filter(|&&slot| slot != REMOVED) {
+            if wander {
+                self.boids.wanders[slot] += self.rng.range(-self.wander_rate, self.wander_rate);
+            }
+            if noise {
+                self.noise_angles[slot] = self.rng.range(-self.noise / 2.0, self.noise / 2.0);
+            }
+        }
+    }
+    /**
+    snapshots every leader before the boids move,
+    and moves them on along their paths
+     */
+    fn update_leaders(&mut self) {
+        for leader in &mut self.leaders {
+            leader.boid = self.boids.get(self.slots[leader.id]);
+            leader.advance(self.waypoint_radius);
+        }
+    }
+    /**
+    leaders seek their next waypoint, the other boids
+    follow the closest leader they can see
+     */
+    fn follow_leaders(&self, i: usize, cur_pos: Vec2, cur_vel: Vec2, params: &BoidParams) -> Vec2 {
+        if self.leaders.is_empty() {
+            return Vec2::zero();
+        }
+        if self.boids.leaders[i] {
+            // only leaders look themselves up
+            let id = self.boids.ids[i];
+            let target = self
+                .leaders
+                .iter()
+                .find(|l| l.id == id)
+                .and_then(Leader::target);
+            return match target {
+                Some(target) if target != cur_pos => {
+                    let desired = (target - cur_pos).normalize() * params.max_speed;
+                    (desired - cur_vel) * self.follow_factor
+                }
+                _ => Vec2::zero(),
+            };
+        }
+        let closest = self
+            .leaders
+            .iter()
+            .map(|l| (l.boid.pos.distance_squared(&cur_pos), l))
+            .filter(|&(dist_sq, _)| dist_sq < params.visible_range * params.visible_range)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        match closest {
+            Some((_, leader)) => leader.follow(cur_pos, self.follow_distance) * self.follow_factor,
+            None => Vec2::zero(),
+        }
+    }
+    /**
+    parameters of the boid in slot i
+     */
+    fn params(&self, i: usize) -> BoidParams {
+        let shared = BoidParams {
+            max_speed: self.max_speed,
+            min_speed: self.min_speed,
+            visible_range: self.visible_range,
+            protect_range: self.protect_range,
+            avoid_factor: self.avoid_factor,
+            align_factor: self.align_factor,
+            cohesion_factor: self.cohesion_factor,
+        };
+        match &self.boids.overrides[i] {
+            Some(overrides) => overrides.apply(shared),
+            None => shared,
+        }
+    }
+    fn pointer_attractor(&self) -> Option<Attractor> {
+        let (pos, mode) = self.pointer?;
+        let strength = match mode {
+            PointerMode::Attract => self.pointer_strength,
+            PointerMode::Repel => -self.pointer_strength,
+            PointerMode::Paint | PointerMode::Erase => return None,
+        };
+        Some(Attractor::new(
+            pos,
+            strength,
+            self.pointer_radius,
+            Falloff::Linear,
+        ))
+    }
+    fn index_len(&self) -> usize {
+        match self.backend {
+            SpatialBackend::QuadTree => self.quadtree.len(),
+            SpatialBackend::SpatialHash => self.spatial_hash.len(),
+        }
+    }
+    /**
+    boids with a finite position, the ones the index holds
+     */
+    fn num_indexable(&self) -> usize {
+        (0..self.boids.len())
+            .filter(|&i| self.boids.pos(i).is_finite())
+            .count()
+    }
+    /**
+    replaces the contents of buf with every boid inside the circle
+     */
+    fn query_neighbours(&self, center: Vec2, radius: f64, buf: &mut Neighbours) {
+        match self.backend {
+            SpatialBackend::QuadTree => gather_neighbours(&self.quadtree, center, radius, buf),
+            SpatialBackend::SpatialHash => {
+                gather_neighbours(&self.spatial_hash, center, radius, buf)
+            }
+        }
+    }
+    /**
+    clears the current spatial index and
+    inserts every boid into it
+     */
+    fn rebuild_index(&mut self, border_top_left: Vec2, border_dims: Vec2) {
+        self.index_dirty = false;
+        match self.backend {
+            SpatialBackend::QuadTree => {
+                // building from boids in morton order
+                // is a lot faster than pushing them
+                self.sort_morton(border_top_left, border_dims);
+                let mut sorted_boids = std::mem::take(&mut self.sorted_boids);
+                sorted_boids.clear();
+                sorted_boids.extend(self.morton_order.iter().map(|&(_, i)| self.boids.get(i)));
+                let built = self
+                    .quadtree
+                    .build(border_top_left, border_dims, &mut sorted_boids);
+                if built.is_err() {
+                    // one bad boid fails the whole build, push
+                    // the others one at a time so only it is left out
+                    fill_index(
+                        &mut self.quadtree,
+                        border_top_left,
+                        border_dims,
+                        sorted_boids.iter().copied(),
+                    );
+                }
+                self.sorted_boids = sorted_boids;
+            }
+            SpatialBackend::SpatialHash => {
+                // cells the size of the query radius means
+                // a query only touches the surrounding cells
+                self.spatial_hash
+                    .set_cell_size(f64::max(self.visible_range, self.protect_range));
+                fill_index(
+                    &mut self.spatial_hash,
+                    border_top_left,
+                    border_dims,
+                    (0..self.boids.len()).map(|i| self.boids.get(i)),
+                );
+            }
+        }
+    }
+    /**
+    fills morton_order with the key and slot of
+    every boid, sorted by key
+     */
+    fn sort_morton(&mut self, top_left: Vec2, dims: Vec2) {
+        self.morton_order.clear();
+        for i in 0..self.boids.len() {
+            let key = morton_key(self.boids.pos(i), top_left, dims);
+            self.morton_order.push((key, i));
+        }
+        // slots are unique, so ties in the key
+        // still sort the same every time
+        self.morton_order.sort_unstable();
+    }
+    /**
+    sorts the flock along the z-order curve
+     */
+    fn reorder_boids(&mut self, top_left: Vec2, dims: Vec2) {
+        self.sort_morton(top_left, dims);
+        let order: Vec<usize> = self.morton_order.iter().map(|&(_, i)| i).collect();
+        self.boids.permute(&order);
+        for (slot, &id) in self.boids.ids.iter().enumerate() {
+            self.slots[id] = slot;
+        }
     }
 
-    fn separation_brute_force(&self, i: usize) -> Vec2 {
+    fn separation_brute_force(&self, i: usize, params: &BoidParams) -> Vec2 {
+        let protect_sq = params.protect_range * params.protect_range;
         let mut close = Vec2::zero();
         for j in 0..self.boids.len() {
             if i == j {
                 continue;
             }
-            let dis = self.boids[j].pos.distance(&self.boids[i].pos);
-            if dis < self.protect_range {
-                close -= self.boids[j].pos - self.boids[i].pos;
+            let dis = self.boids.pos(j).distance(&self.boids.pos(i));
+            // boids on top of each other have no direction to push in
+            if dis > 0.0 && dis < params.protect_range {
+                let weight = self.separation_falloff.weight(dis * dis, protect_sq);
+                close -= (self.boids.pos(j) - self.boids.pos(i)) * weight;
             }
         }
-        close * self.avoid_factor
+        close * params.avoid_factor
     }
-    fn separation(&self, cur_pos: Vec2, protected_boids: &Vec<Boid>) -> Vec2 {
-        // visible boids includes the current boid
-        let mut close = Vec2::zero();
-        for boid in protected_boids {
-            close += cur_pos - boid.pos;
-        }
-        close * self.avoid_factor
+    fn separation(&self, sums: &NeighbourSums, params: &BoidParams) -> Vec2 {
+        // the current boid is in sums.close,
+        // but contributes nothing
+        sums.close * params.avoid_factor
     }
-    fn alignment_brute_force(&self, i: usize) -> Vec2 {
+    fn alignment_brute_force(&self, i: usize, params: &BoidParams) -> Vec2 {
         let mut vel_avg = Vec2::zero();
         let mut neighbours = 0;
         for j in 0..self.boids.len() {
             if i == j {
                 continue;
             }
-            let dis = self.boids[i].pos.distance(&self.boids[j].pos);
-            if dis < self.visible_range {
+            let dis = self.boids.pos(i).distance(&self.boids.pos(j));
+            if dis < params.visible_range {
                 neighbours += 1;
-                vel_avg += self.boids[j].vel;
+                vel_avg += self.boids.vel(j);
             }
         }
         if neighbours == 0 {
             return Vec2::zero();
         }
         vel_avg /= neighbours as f64;
-        (vel_avg - self.boids[i].vel) * self.align_factor
+        (vel_avg - self.boids.vel(i)) * params.align_factor
     }
-    fn alignment(&self, cur_vel: Vec2, visible_boids: &Vec<Boid>) -> Vec2 {
-        if visible_boids.len() <= 1 {
+    fn alignment(&self, cur_vel: Vec2, sums: &NeighbourSums, params: &BoidParams) -> Vec2 {
+        if sums.visible <= 1 {
             return Vec2::zero();
         }
-        let mut vel_avg = -cur_vel;
-        for boid in visible_boids {
-            vel_avg += boid.vel;
-        }
-        vel_avg /= visible_boids.len() as f64 - 1.0; // -1.0 to exclude current boid
-        vel_avg * self.align_factor
+        // -1.0 to exclude current boid
+        let vel_avg = (sums.vel - cur_vel) / (sums.visible as f64 - 1.0);
+        vel_avg * params.align_factor
     }
-    fn cohesion_brute_force(&self, i: usize) -> Vec2 {
+    fn cohesion_brute_force(&self, i: usize, params: &BoidParams) -> Vec2 {
         let mut pos_avg = Vec2::zero();
         let mut neighbours = 0;
         for j in 0..self.boids.len() {
             if i == j {
                 continue;
             }
-            let dis = self.boids[i].pos.distance(&self.boids[j].pos);
-            if dis < self.visible_range {
+            let dis = self.boids.pos(i).distance(&self.boids.pos(j));
+            if dis < params.visible_range {
                 neighbours += 1;
-                pos_avg += self.boids[j].pos;
+                pos_avg += self.boids.pos(j);
             }
         }
         if neighbours == 0 {
             return Vec2::zero();
         }
         pos_avg /= neighbours as f64;
-        (pos_avg - self.boids[i].pos) * self.cohesion_factor
+        (pos_avg - self.boids.pos(i)) * params.cohesion_factor
+    }
+    fn cohesion(&self, cur_pos: Vec2, sums: &NeighbourSums, params: &BoidParams) -> Vec2 {
+        if sums.visible <= 1 {
+            return Vec2::zero();
+        }
+        // -1.0 to exclude current boid
+        let pos_avg = (sums.pos - cur_pos) / (sums.visible as f64 - 1.0);
+        (pos_avg - cur_pos) * params.cohesion_factor
+    }
+    fn attraction(&self, cur_pos: Vec2) -> Vec2 {
+        self.attractors
+            .iter()
+            .flatten()
+            .chain(self.pointer_attractor().as_ref())
+            .fold(Vec2::zero(), |acc, attractor| {
+                acc + attractor.force(cur_pos)
+            })
+    }
+    /**
+    reynolds wander, steers towards the boid's
+    point on a unit circle ahead of it
+     */
+    fn wander(&self, i: usize, cur_vel: Vec2) -> Vec2 {
+        if self.wander_strength == 0.0 {
+            return Vec2::zero();
+        }
+        // a boid at rest wanders from facing right
+        let heading = if cur_vel == Vec2::zero() {
+            Vec2::new(1.0, 0.0)
+        } else {
+            cur_vel.normalize()
+        };
+        let target = heading * WANDER_DISTANCE + heading.rotate(self.boids.wanders[i]);
+        target.normalize() * self.wander_strength
+    }
+    /**
+    steers towards the closest food, harder the hungrier
+    the boid is. nothing once the boid is on the food
+     */
+    fn seek_food(&self, i: usize, cur_pos: Vec2, cur_vel: Vec2, params: &BoidParams) -> Vec2 {
+        let closest = self
+            .foods
+            .iter()
+            .flatten()
+            .map(|food| (food.pos.distance_squared(&cur_pos), food))
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        let Some((_, food)) = closest else {
+            return Vec2::zero();
+        };
+        if food.contains(cur_pos) || self.max_energy <= 0.0 {
+            return Vec2::zero();
+        }
+        let hunger = (1.0 - self.boids.energies[i] / self.max_energy).clamp(0.0, 1.0);
+        let desired = (food.pos - cur_pos).normalize() * params.max_speed;
+        (desired - cur_vel) * (self.food_factor * hunger)
+    }
+    /**
+    burns energy for the distance moved, feeds the boids
+    on food, and removes the ones that ran out
+     */
+    fn update_energy(&mut self) {
+        self.starved = 0;
+        // with nothing to burn or eat energies only change
+        // through set_boid_energy, starving is still checked
+        let changing = self.metabolism != 0.0 || self.num_foods() > 0;
+        if changing {
+            for i in 0..self.boids.len() {
+                let pos = self.boids.pos(i);
+                let eaten: f64 = self
+                    .foods
+                    .iter()
+                    .flatten()
+                    .filter(|food| food.contains(pos))
+                    .map(|food| food.rate)
+                    .sum();
+                let energy =
+                    self.boids.energies[i] - self.metabolism * self.boids.vel(i).length() + eaten;
+                self.boids.energies[i] = energy.min(self.max_energy);
+            }
+        }
+        // collected first, removing moves boids between slots
+        let starving: Vec<usize> = (0..self.boids.len())
+            .filter(|&i| self.boids.energies[i] <= 0.0)
+            .map(|i| self.boids.ids[i])
+            .collect();
+        for &id in &starving {
+            self.remove_from_flock(id);
+        }
+        self.starved = starving.len();
+    }
+    /**
+    ages every boid, removes the ones past max_age
+    and lets the others have children
+     */
+    fn update_lifecycle(&mut self) {
+        for age in &mut self.boids.ages {
+            *age += 1;
+        }
+        let mut deaths = 0;
+        if self.max_age > 0 {
+            let old: Vec<usize> = (0..self.boids.len())
+                .filter(|&i| self.boids.ages[i] >= self.max_age)
+                .map(|i| self.boids.ids[i])
+                .collect();
+            for &id in &old {
+                self.remove_from_flock(id);
+            }
+            deaths = old.len();
+        }
+        self.deaths = self.starved + deaths;
+
+        self.births = 0;
+        if self.birth_rate <= 0.0 {
+            return;
+        }
+        // in id order, so the random numbers go to the same
+        // boids however the flock is sorted
+        let mut parents: Vec<usize> = (0..self.boids.len())
+            .filter(|&i| {
+                self.boids.ages[i] >= self.maturity && self.boids.energies[i] > self.birth_cost
+            })
+            .map(|i| self.boids.ids[i])
+            .collect();
+        parents.sort_unstable();
+        for id in parents {
+            if self.boids.len() >= self.max_population {
+                break;
+            }
+            if self.rng.next_f64() < self.birth_rate {
+                self.reproduce(id);
+                self.births += 1;
+            }
+        }
+    }
+    /**
+    spawns a child of the boid near it, moving the same way,
+    with the parent's parameters slightly mutated
+     */
+    fn reproduce(&mut self, id: usize) {
+        let slot = self.slots[id];
+        let parent = self.boids.get(slot);
+        let params = self.params(slot);
+        let mass = self.boids.masses[slot];
+        self.boids.energies[slot] -= self.birth_cost;
+
+        let pos = parent.pos + self.rng.in_disc(params.protect_range);
+        let child = self.spawn(Boid::new(pos, parent.vel));
+        let child_slot = self.slots[child];
+        self.boids.masses[child_slot] = mass;
+        // mutations scale the parent's values instead of replacing
+        // them, so changes to the world's values still reach the child
+        let mut overrides = self.boids.overrides[slot]
+            .as_deref()
+            .cloned()
+            .unwrap_or_default();
+        if self.mutation > 0.0 {
+            for param in PARAMS {
+                let scale = self.rng.range(1.0 - self.mutation, 1.0 + self.mutation);
+                overrides.scale(param, scale);
+            }
+        }
+        if !overrides.is_empty() {
+            self.boids.overrides[child_slot] = Some(Box::new(overrides));
+        }
     }
-    fn cohesion(&self, cur_pos: Vec2, visible_boids: &Vec<Boid>) -> Vec2 {
-        if visible_boids.len() <= 1 {
+    /**
+    reynolds path following, seeks a point further along the
+    path once the boid is predicted to drift off it
+     */
+    fn follow_path(&self, cur_pos: Vec2, cur_vel: Vec2, params: &BoidParams) -> Vec2 {
+        let Some(path) = &self.path else {
             return Vec2::zero();
+        };
+        match path.target(cur_pos, cur_vel, self.path_lookahead) {
+            Some(target) if target != cur_pos => {
+                let desired = (target - cur_pos).normalize() * params.max_speed;
+                (desired - cur_vel) * self.path_factor
+            }
+            _ => Vec2::zero(),
+        }
+    }
+    /**
+    combines the steering of every rule, ordered by priority,
+    into the change in velocity of boid i
+     */
+    fn acceleration(&self, i: usize, steering: &[Vec2]) -> Vec2 {
+        let force = if self.prioritized {
+            // each rule takes what it needs from the budget,
+            // the rules after the one that runs out get nothing
+            let mut budget = self.max_force;
+            let mut total = Vec2::zero();
+            for &force in steering {
+                let len = force.length();
+                if len > budget {
+                    total += force * (budget / len);
+                    break;
+                }
+                total += force;
+                budget -= len;
+            }
+            total
+        } else {
+            steering
+                .iter()
+                .fold(Vec2::zero(), |acc, &force| acc + force)
+                .clamp_length(0.0, self.max_force)
+        };
+        force / self.boids.masses[i]
+    }
+    /**
+    vicsek style noise, turns the new velocity of boid i
+     */
+    fn add_noise(&self, i: usize, vel: Vec2) -> Vec2 {
+        match self.noise_angles.get(i) {
+            Some(&angle) => vel.rotate(angle),
+            None => vel,
         }
-        let mut pos_avg = -cur_pos;
-        for boid in visible_boids {
-            pos_avg += boid.pos;
+    }
+    /**
+    wind plus the flow field at the boid
+     */
+    fn currents(&self, cur_pos: Vec2) -> Vec2 {
+        match &self.flow_field {
+            Some(field) => self.wind + field.sample(cur_pos),
+            None => self.wind,
         }
-        pos_avg /= visible_boids.len() as f64 - 1.0; // -1.0 to exclude current boid
-        (pos_avg - cur_pos) * self.cohesion_factor
     }
     fn handle_margins(&self, cur_pos: Vec2) -> Vec2 {
         Vec2::new(
@@ -185,63 +1364,254 @@ impl World {
         )
     }
     pub fn tick_brute_force(&mut self) {
+        self.apply_pointer();
+        self.draw_randomness();
+        self.update_leaders();
         for i in 0..self.boids.len() {
+            let params = self.params(i);
+
             // calculate updated velocity
-            let sep = self.separation_brute_force(i);
-            let align = self.alignment_brute_force(i);
-            let cohesion = self.cohesion_brute_force(i);
-            let turn = self.handle_margins(self.boids[i].pos);
-            self.boids[i].vel += sep + align + cohesion + turn;
+            let sep = self.separation_brute_force(i, &params);
+            let align = self.alignment_brute_force(i, &params);
+            let cohesion = self.cohesion_brute_force(i, &params);
+            let attract = self.attraction(self.boids.pos(i));
+            let food = self.seek_food(i, self.boids.pos(i), self.boids.vel(i), &params);
+            let current = self.currents(self.boids.pos(i));
+            let wander = self.wander(i, self.boids.vel(i));
+            let follow = self.follow_leaders(i, self.boids.pos(i), self.boids.vel(i), &params);
+            let path = self.follow_path(self.boids.pos(i), self.boids.vel(i), &params);
+            let turn = self.handle_margins(self.boids.pos(i));
+            let steering = [
+                turn, sep, align, cohesion, attract, food, follow, path, wander,
+            ];
+            let mut vel = self.boids.vel(i) + self.acceleration(i, &steering) + current;
+            vel = self.add_noise(i, vel);
 
             // constrain velocity
-            self.boids[i].vel = self.boids[i]
-                .vel
-                .clamp_length(self.min_speed, self.max_speed);
+            vel = vel.clamp_length(params.min_speed, params.max_speed);
 
             // update position
-            let vel = self.boids[i].vel;
-            self.boids[i].pos += vel;
+            self.boids.set(i, Boid::new(self.boids.pos(i) + vel, vel));
         }
     }
-    pub fn tick(&mut self) {
+    /**
+    bounding box of all boids, padded by 0.5
+    so that every boid is strictly inside
+     */
+    fn boid_bounds(&self) -> (Vec2, Vec2) {
+        if self.boids.len() == 0 {
+            return (Vec2::zero(), self.dims);
+        }
         let mut tl = Vec2::from(f64::INFINITY);
         let mut br = Vec2::from(f64::NEG_INFINITY);
+        for (&x, &y) in self.boids.xs.iter().zip(&self.boids.ys) {
+            tl.x = f64::min(tl.x, x);
+            tl.y = f64::min(tl.y, y);
+            br.x = f64::max(br.x, x);
+            br.y = f64::max(br.y, y);
+        }
+        (tl - 0.5, br - tl + 1.0)
+    }
+    /**
+    moves every overlapping pair of boids apart along
+    the line between them until they just touch
+     */
+    fn resolve_collisions(&mut self) {
+        let (tl, dims) = self.boid_bounds();
+        self.collision_tree.reset(tl, dims);
+        for idx in 0..self.boids.len() {
+            self.collision_tree.push(&BoidBody {
+                idx,
+                pos: self.boids.pos(idx),
+                radius: self.boid_radius,
+            });
+        }
+        debug_assert_eq!(self.collision_tree.len(), self.num_indexable());
 
-        // update the boids
+        // corrections are applied after all pairs are
+        // found, so the result does not depend on order
+        let mut corrections = std::mem::take(&mut self.corrections);
+        corrections.clear();
+        corrections.resize(self.boids.len(), Vec2::zero());
         for i in 0..self.boids.len() {
-            let boid = &self.boids[i];
-            let visible = self.quadtree.query_circle(boid.pos, self.visible_range);
-            let protected = self.quadtree.query_circle(boid.pos, self.protect_range);
+            let pos = self.boids.pos(i);
+            self.collision_tree
+                .overlapping_circle_with(pos, self.boid_radius, |other| {
+                    // handle each pair once
+                    if other.idx <= i {
+                        return;
+                    }
+                    let diff = pos - other.pos;
+                    let dist = diff.length();
+                    let dir = if dist > 0.0 {
+                        diff / dist
+                    } else {
+                        // coincident boids, any direction works
+                        Vec2::new(1.0, 0.0)
+                    };
+                    let push = dir * ((self.boid_radius + other.radius - dist) / 2.0);
+                    corrections[i] += push;
+                    corrections[other.idx] -= push;
+                });
+        }
+        for (i, correction) in corrections.iter().enumerate() {
+            self.boids.set_pos(i, self.boids.pos(i) + *correction);
+        }
+        self.corrections = corrections;
+    }
+    /**
+    next state of boid i, neighbours are found in the
+    spatial index and include the boid itself
+     */
+    fn next_boid(&self, i: usize, neighbours: &mut Neighbours) -> Boid {
+        let pos = self.boids.pos(i);
+        let vel = self.boids.vel(i);
+        let params = self.params(i);
+        // a single query covers the ranges of all rules,
+        // each rule filters by its own range
+        self.query_neighbours(pos, params.query_range(), neighbours);
+        let sums = neighbours.accumulate(
+            pos,
+            params.protect_range * params.protect_range,
+            params.visible_range * params.visible_range,
+            self.separation_falloff,
+        );
 
-            // calculate updated velocity
-            let sep = self.separation(boid.pos, &protected);
-            let align = self.alignment(boid.vel, &visible);
-            let cohesion = self.cohesion(boid.pos, &visible);
-            let turn = self.handle_margins(boid.pos);
-            let mut vel = boid.vel + sep + align + cohesion + turn;
+        // calculate updated velocity
+        let sep = self.separation(&sums, &params);
+        let align = self.alignment(vel, &sums, &params);
+        let cohesion = self.cohesion(pos, &sums, &params);
+        let attract = self.attraction(pos);
+        let food = self.seek_food(i, pos, vel, &params);
+        let current = self.currents(pos);
+        let wander = self.wander(i, vel);
+        let follow = self.follow_leaders(i, pos, vel, &params);
+        let path = self.follow_path(pos, vel, &params);
+        let turn = self.handle_margins(pos);
+        let steering = [
+            turn, sep, align, cohesion, attract, food, follow, path, wander,
+        ];
+        let mut vel = vel + self.acceleration(i, &steering) + current;
+        vel = self.add_noise(i, vel);
 
-            // constrain velocity
-            vel = vel.clamp_length(self.min_speed, self.max_speed);
+        // constrain velocity
+        vel = vel.clamp_length(params.min_speed, params.max_speed);
+
+        Boid::new(pos + vel, vel)
+    }
+    fn update_boids(&mut self) {
+        let mut neighbours = std::mem::take(&mut self.neighbours);
+
+        // the spatial index still holds the previous frame,
+        // so updating in place reads the same state as a
+        // separate buffer would
+        for i in 0..self.boids.len() {
+            let boid = self.next_boid(i, &mut neighbours);
+            self.boids.set(i, boid);
+        }
+        self.neighbours = neighbours;
+    }
+    #[cfg(feature = "parallel")]
+    fn update_boids_parallel(&mut self) {
+        use rayon::prelude::*;
+
+        let mut next_boids = std::mem::take(&mut self.next_boids);
+        // only for the length, every value is overwritten
+        next_boids.clone_from(&self.boids);
+        (
+            next_boids.xs.par_iter_mut(),
+            next_boids.ys.par_iter_mut(),
+            next_boids.vxs.par_iter_mut(),
+            next_boids.vys.par_iter_mut(),
+        )
+            .into_par_iter()
+            .enumerate()
+            .for_each_init(Neighbours::default, |neighbours, (i, (x, y, vx, vy))| {
+                let boid = self.next_boid(i, neighbours);
+                (*x, *y) = (boid.pos.x, boid.pos.y);
+                (*vx, *vy) = (boid.vel.x, boid.vel.y);
+            });
+        std::mem::swap(&mut self.boids, &mut next_boids);
+        self.next_boids = next_boids;
+    }
+    pub fn tick(&mut self) {
+        self.apply_pointer();
+        self.refresh_index();
+        self.draw_randomness();
+        self.update_leaders();
 
-            // update boid
-            self.boids[i].pos += vel;
-            self.boids[i].vel = vel;
+        #[cfg(feature = "parallel")]
+        if self.boids.len() >= PARALLEL_MIN_BOIDS {
+            self.update_boids_parallel();
+        } else {
+            self.update_boids();
+        }
+        #[cfg(not(feature = "parallel"))]
+        self.update_boids();
 
-            // update corners of quadtree
-            tl.x = f64::min(tl.x, self.boids[i].pos.x);
-            tl.y = f64::min(tl.y, self.boids[i].pos.y);
-            br.x = f64::max(br.x, self.boids[i].pos.x);
-            br.y = f64::max(br.y, self.boids[i].pos.y);
+        self.finish_tick();
+    }
+    /**
+    everything in a tick after the boids have moved
+     */
+    fn finish_tick(&mut self) {
+        self.update_energy();
+        self.update_lifecycle();
+        if self.collisions {
+            self.resolve_collisions();
         }
 
-        // rebuild the quadtree
-        // add 0.5 offset boundaries to ensure
-        // that the boids are inside the quadtree
-        self.quadtree.reset(tl - 0.5, br - tl + 1.0);
-        for boid in &self.boids {
-            let success = self.quadtree.push(boid);
-            debug_assert!(success);
+        let (tl, dims) = self.boid_bounds();
+        self.ticks += 1;
+        if self.reorder_interval > 0 && self.ticks % self.reorder_interval == 0 {
+            self.reorder_boids(tl, dims);
         }
+
+        // rebuild the spatial index
+        self.rebuild_index(tl, dims);
+    }
+}
+
+/**
+replaces the contents of buf with every boid
+of the index inside the circle
+ */
+fn gather_neighbours<I>(index: &I, center: Vec2, radius: f64, buf: &mut Neighbours)
+where
+    I: SpatialIndex<Boid>,
+{
+    buf.clear();
+    let mut stack = std::mem::take(&mut buf.stack);
+    index.query_circle_with(center, radius, &mut stack, |boid| {
+        buf.push(boid.pos, boid.vel)
+    });
+    buf.stack = stack;
+}
+
+/**
+empties the index over the new borders and pushes every
+boid with a finite position, the borders must hold them all
+ */
+fn fill_index<I, B>(index: &mut I, border_top_left: Vec2, border_dims: Vec2, boids: B)
+where
+    I: SpatialIndex<Boid>,
+    B: Iterator<Item = Boid>,
+{
+    index.reset(border_top_left, border_dims);
+    for boid in boids.filter(|boid| boid.pos.is_finite()) {
+        let success = index.push(&boid);
+        debug_assert!(success);
+    }
+}
+
+#[cfg(feature = "bench")]
+impl World {
+    /**
+    World::rebuild for the benches, kept out
+    of the wasm_bindgen impl so js never sees it
+     */
+    pub fn bench_rebuild(&mut self) {
+        self.rebuild();
     }
 }
 
@@ -264,12 +1634,18 @@ fn test_world_separation() {
             world.add_boid(&Vec2::new(i as f64, j as f64), &Vec2::zero());
         }
     }
+    let mut protected = Neighbours::default();
     for idx in 0..world.num_boids() {
-        let protected = world
-            .quadtree
-            .query_circle(world.boids[idx].pos, world.protect_range);
-        let sep = world.separation(world.boids[idx].pos, &protected);
-        let expected = world.separation_brute_force(idx);
+        let pos = world.boids.pos(idx);
+        world.query_neighbours(pos, world.protect_range, &mut protected);
+        let sums = protected.accumulate(
+            pos,
+            world.protect_range.powi(2),
+            0.0,
+            world.separation_falloff,
+        );
+        let sep = world.separation(&sums, &world.params(idx));
+        let expected = world.separation_brute_force(idx, &world.params(idx));
         assert_eq!(sep, expected);
     }
 }
@@ -293,12 +1669,18 @@ fn test_world_alignment() {
             world.add_boid(&Vec2::new(i as f64, j as f64), &Vec2::zero());
         }
     }
+    let mut visible = Neighbours::default();
     for idx in 0..world.num_boids() {
-        let visible = world
-            .quadtree
-            .query_circle(world.boids[idx].pos, world.visible_range);
-        let align = world.alignment(world.boids[idx].vel, &visible);
-        let expected = world.alignment_brute_force(idx);
+        let pos = world.boids.pos(idx);
+        world.query_neighbours(pos, world.visible_range, &mut visible);
+        let sums = visible.accumulate(
+            pos,
+            0.0,
+            world.visible_range.powi(2),
+            world.separation_falloff,
+        );
+        let align = world.alignment(world.boids.vel(idx), &sums, &world.params(idx));
+        let expected = world.alignment_brute_force(idx, &world.params(idx));
         assert_eq!(align, expected);
     }
 }
@@ -322,16 +1704,74 @@ fn test_world_cohesion() {
             world.add_boid(&Vec2::new(i as f64, j as f64), &Vec2::zero());
         }
     }
+    let mut visible = Neighbours::default();
     for idx in 0..world.num_boids() {
-        let visible = world
-            .quadtree
-            .query_circle(world.boids[idx].pos, world.visible_range);
-        let cohesion = world.cohesion(world.boids[idx].pos, &visible);
-        let expected = world.cohesion_brute_force(idx);
+        let pos = world.boids.pos(idx);
+        world.query_neighbours(pos, world.visible_range, &mut visible);
+        let sums = visible.accumulate(
+            pos,
+            0.0,
+            world.visible_range.powi(2),
+            world.separation_falloff,
+        );
+        let cohesion = world.cohesion(pos, &sums, &world.params(idx));
+        let expected = world.cohesion_brute_force(idx, &world.params(idx));
         assert_eq!(cohesion, expected);
     }
 }
 
+#[test]
+fn test_world_combined_query() {
+    // tick queries once with the larger range
+    // and each rule filters by its own range
+    let mut world = World::new(
+        &Vec2::new(100.0, 100.0),
+        25.0,
+        12.0,
+        1.0,
+        1.0,
+        1.0,
+        0.0,
+        0.0,
+        10.0,
+        0.0,
+    );
+    for i in (0..100).step_by(10) {
+        for j in (0..100).step_by(10) {
+            world.add_boid(
+                &Vec2::new(i as f64, j as f64),
+                &Vec2::new(j as f64, -(i as f64)),
+            );
+        }
+    }
+    let query_range = f64::max(world.visible_range, world.protect_range);
+    let mut neighbours = Neighbours::default();
+    for idx in 0..world.num_boids() {
+        let boid = world.get_boid(idx);
+        world.query_neighbours(boid.pos, query_range, &mut neighbours);
+        let sums = neighbours.accumulate(
+            boid.pos,
+            world.protect_range.powi(2),
+            world.visible_range.powi(2),
+            world.separation_falloff,
+        );
+        assert_eq!(
+            world.separation(&sums, &world.params(idx)),
+            world.separation_brute_force(idx, &world.params(idx))
+        );
+        // the tick adds the average velocity of the neighbours,
+        // the brute force steers towards it
+        let params = world.params(idx);
+        let expected = world.alignment_brute_force(idx, &params) + boid.vel * params.align_factor;
+        let alignment = world.alignment(boid.vel, &sums, &params);
+        assert!(alignment.distance(&expected) < 1e-9);
+        assert_eq!(
+            world.cohesion(boid.pos, &sums, &world.params(idx)),
+            world.cohesion_brute_force(idx, &world.params(idx))
+        );
+    }
+}
+
 #[test]
 fn test_world_tick() {
     // world tick stress test
@@ -358,3 +1798,1051 @@ fn test_world_tick() {
     }
     assert_eq!(world.num_boids(), 100);
 }
+
+#[test]
+fn test_world_coincident_boids() {
+    let mut world = World::new(
+        &Vec2::new(100.0, 100.0),
+        15.0,
+        5.0,
+        0.05,
+        0.05,
+        0.0005,
+        40.0,
+        0.2,
+        6.0,
+        2.0,
+    );
+    world.set_quadtree_params(8, 10);
+    assert_eq!(world.quadtree_max_points(), 8);
+    assert_eq!(world.quadtree_max_depth(), 10);
+    for _ in 0..100 {
+        world.add_boid(&Vec2::from(50.0), &Vec2::new(1.0, 0.0));
+    }
+    for _ in 0..10 {
+        world.tick();
+    }
+    assert_eq!(world.num_boids(), 100);
+    assert_eq!(world.quadtree_max_points(), 8);
+}
+
+#[test]
+fn test_world_spatial_hash() {
+    let new_world = || {
+        let mut world = World::new(
+            &Vec2::new(100.0, 100.0),
+            15.0,
+            5.0,
+            0.05,
+            0.05,
+            0.0005,
+            40.0,
+            0.2,
+            6.0,
+            2.0,
+        );
+        for i in (0..100).step_by(10) {
+            for j in (0..100).step_by(10) {
+                // rotating around a point between boids, a boid
+                // at rest would turn wherever rounding points it
+                world.add_boid(
+                    &Vec2::new(i as f64, j as f64),
+                    &Vec2::new((j as f64 - 45.0) / 25.0, (45.0 - i as f64) / 25.0),
+                );
+            }
+        }
+        world
+    };
+    let mut quadtree_world = new_world();
+    let mut hash_world = new_world();
+    hash_world.set_backend(SpatialBackend::SpatialHash);
+    assert_eq!(hash_world.backend(), SpatialBackend::SpatialHash);
+    assert_eq!(hash_world.spatial_hash.len(), 100);
+
+    // both backends find the same neighbours, only
+    // the order they are summed in may differ
+    for _ in 0..50 {
+        quadtree_world.tick();
+        hash_world.tick();
+    }
+    for i in 0..quadtree_world.num_boids() {
+        let a = quadtree_world.get_boid(i);
+        let b = hash_world.get_boid(i);
+        assert!(a.pos.distance(&b.pos) < 1e-6);
+        assert!(a.vel.distance(&b.vel) < 1e-6);
+    }
+
+    assert!(hash_world.quadtree_stats().is_none());
+    hash_world.set_backend(SpatialBackend::QuadTree);
+    assert_eq!(hash_world.quadtree.len(), 100);
+    assert_eq!(
+        hash_world.quadtree_stats(),
+        Some(quadtree_world.quadtree.stats())
+    );
+    hash_world.tick();
+    assert_eq!(hash_world.num_boids(), 100);
+}
+
+#[test]
+fn test_world_add_boid_after_tick() {
+    // the spatial index shrinks to the boids after a tick,
+    // boids added outside of it must still be found
+    for backend in [SpatialBackend::QuadTree, SpatialBackend::SpatialHash] {
+        let mut world = World::new(
+            &Vec2::new(100.0, 100.0),
+            15.0,
+            5.0,
+            0.05,
+            0.05,
+            0.0005,
+            40.0,
+            0.2,
+            6.0,
+            2.0,
+        );
+        world.set_backend(backend);
+        world.add_boid(&Vec2::from(50.0), &Vec2::new(2.0, 0.0));
+        world.add_boid(&Vec2::from(52.0), &Vec2::new(2.0, 0.0));
+        world.tick();
+
+        world.add_boid(&Vec2::new(5.0, 90.0), &Vec2::new(0.0, 2.0));
+        world.add_boid(&Vec2::new(-20.0, 130.0), &Vec2::new(0.0, 2.0));
+        assert_eq!(world.num_boids(), 4);
+        let mut found = Neighbours::default();
+        world.query_neighbours(Vec2::new(-20.0, 130.0), 1.0, &mut found);
+        assert_eq!(found.len(), 1);
+        world.tick();
+        assert_eq!(world.num_boids(), 4);
+
+        // a tick without boids leaves nothing to shrink to
+        for id in 0..4 {
+            world.remove_boid(id);
+        }
+        world.tick();
+        world.add_boid(&Vec2::new(70.0, 20.0), &Vec2::new(2.0, 0.0));
+        world.query_neighbours(Vec2::new(70.0, 20.0), 1.0, &mut found);
+        assert_eq!(found.len(), 1);
+        world.tick();
+        assert_eq!(world.num_boids(), 1);
+    }
+}
+
+#[test]
+fn test_world_collisions() {
+    // no flocking forces, only collisions
+    let mut world = World::new(
+        &Vec2::new(100.0, 100.0),
+        15.0,
+        5.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        10.0,
+        0.0,
+    );
+    world.boid_radius = 2.0;
+    world.add_boid(&Vec2::new(50.0, 50.0), &Vec2::zero());
+    world.add_boid(&Vec2::new(51.0, 50.0), &Vec2::zero());
+    world.add_boid(&Vec2::new(20.0, 20.0), &Vec2::zero());
+    world.add_boid(&Vec2::new(20.0, 20.0), &Vec2::zero());
+    world.add_boid(&Vec2::new(80.0, 80.0), &Vec2::zero());
+
+    // off by default
+    world.tick();
+    assert_eq!(world.get_boid(1).pos, Vec2::new(51.0, 50.0));
+
+    world.collisions = true;
+    world.tick();
+    // overlapping pairs move apart symmetrically until touching
+    assert_eq!(world.get_boid(0).pos, Vec2::new(48.5, 50.0));
+    assert_eq!(world.get_boid(1).pos, Vec2::new(52.5, 50.0));
+    assert_eq!(world.get_boid(2).pos.distance(&world.get_boid(3).pos), 4.0);
+    // others are untouched
+    assert_eq!(world.get_boid(4).pos, Vec2::new(80.0, 80.0));
+
+    // the spatial index is rebuilt with the corrected positions
+    let mut found = Neighbours::default();
+    world.query_neighbours(Vec2::new(52.5, 50.0), 0.1, &mut found);
+    assert_eq!(found.len(), 1);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_world_tick_parallel() {
+    let new_world = || {
+        let mut world = World::new(
+            &Vec2::new(200.0, 200.0),
+            32.0,
+            8.0,
+            0.05,
+            0.05,
+            0.0005,
+            25.0,
+            0.2,
+            4.0,
+            1.0,
+        );
+        for i in 0..40 {
+            for j in 0..40 {
+                world.add_boid(
+                    &Vec2::new(i as f64 * 5.0 + j as f64 * 0.01, j as f64 * 5.0),
+                    &Vec2::new((i % 7) as f64 - 3.0, (j % 5) as f64 - 2.0),
+                );
+            }
+        }
+        world
+    };
+    let mut sequential = new_world();
+    let mut parallel = new_world();
+    assert!(parallel.num_boids() >= PARALLEL_MIN_BOIDS);
+    for _ in 0..30 {
+        sequential.update_boids();
+        sequential.finish_tick();
+        parallel.tick();
+    }
+    // bit for bit the same
+    for i in 0..sequential.num_boids() {
+        assert_eq!(sequential.get_boid(i).pos, parallel.get_boid(i).pos);
+        assert_eq!(sequential.get_boid(i).vel, parallel.get_boid(i).vel);
+    }
+}
+
+#[test]
+fn test_world_boid_data() {
+    let mut world = World::new(
+        &Vec2::new(100.0, 100.0),
+        15.0,
+        5.0,
+        0.05,
+        0.05,
+        0.0005,
+        40.0,
+        0.2,
+        6.0,
+        2.0,
+    );
+    assert!(world.boid_data().is_empty());
+    world.add_boid(&Vec2::new(1.0, 2.0), &Vec2::new(3.0, 4.0));
+    world.add_boid(&Vec2::new(5.0, 6.0), &Vec2::new(7.0, 8.0));
+    assert_eq!(
+        world.boid_data(),
+        vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]
+    );
+}
+
+#[test]
+fn test_world_reorder() {
+    let new_world = |reorder_interval| {
+        let mut world = World::new(
+            &Vec2::new(200.0, 200.0),
+            32.0,
+            8.0,
+            0.05,
+            0.05,
+            0.0005,
+            25.0,
+            0.2,
+            4.0,
+            1.0,
+        );
+        world.reorder_interval = reorder_interval;
+        // added column by column, far from morton order
+        for i in 0..20 {
+            for j in 0..20 {
+                let id = world.add_boid(
+                    &Vec2::new(i as f64 * 10.0 + j as f64 * 0.01, j as f64 * 10.0),
+                    &Vec2::new((i % 7) as f64 - 3.0, (j % 5) as f64 - 2.0),
+                );
+                assert_eq!(id, i * 20 + j);
+            }
+        }
+        world
+    };
+    let mut plain = new_world(0);
+    let mut reordered = new_world(1);
+    reordered.tick();
+    plain.tick();
+    assert_ne!(reordered.boids.ids, plain.boids.ids);
+
+    // sorted by morton key of the positions after the tick
+    let (tl, dims) = reordered.boid_bounds();
+    let keys: Vec<u32> = (0..reordered.num_boids())
+        .map(|i| morton_key(reordered.boids.pos(i), tl, dims))
+        .collect();
+    assert!(keys.windows(2).all(|w| w[0] <= w[1]));
+
+    for _ in 0..20 {
+        plain.tick();
+        reordered.tick();
+    }
+    // the same boids under the same ids, only
+    // the order of the neighbour sums can differ
+    for id in 0..plain.num_boids() {
+        let a = plain.get_boid(id);
+        let b = reordered.get_boid(id);
+        assert!(a.pos.distance(&b.pos) < 1e-9);
+        assert!(a.vel.distance(&b.vel) < 1e-9);
+    }
+    // boid_data is in id order too
+    let plain_data = plain.boid_data();
+    let reordered_data = reordered.boid_data();
+    for (a, b) in plain_data.iter().zip(&reordered_data) {
+        assert!((a - b).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_world_non_finite_boid() {
+    for backend in [SpatialBackend::QuadTree, SpatialBackend::SpatialHash] {
+        let mut world = World::new(
+            &Vec2::new(100.0, 100.0),
+            15.0,
+            5.0,
+            0.05,
+            0.05,
+            0.0005,
+            40.0,
+            0.2,
+            6.0,
+            2.0,
+        );
+        world.set_backend(backend);
+        world.collisions = true;
+        for i in 0..10 {
+            world.add_boid(&Vec2::new(i as f64 * 10.0, 50.0), &Vec2::new(1.0, 0.0));
+        }
+        // the bad boid is left out of the index,
+        // the others still find each other
+        let bad = world.add_boid(&Vec2::new(f64::NAN, 50.0), &Vec2::zero());
+        for _ in 0..5 {
+            world.tick();
+        }
+        assert_eq!(world.num_boids(), 11);
+        assert_eq!(world.index_len(), 10);
+        assert!(world.get_boid(bad).pos.x.is_nan());
+        for id in 0..10 {
+            let boid = world.get_boid(id);
+            assert!(boid.pos.x.is_finite() && boid.pos.y.is_finite());
+        }
+    }
+}
+
+#[test]
+fn test_world_attractors() {
+    // only the attractors steer
+    let mut world = World::new(
+        &Vec2::new(100.0, 100.0),
+        15.0,
+        5.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        10.0,
+        0.0,
+    );
+    world.add_boid(&Vec2::new(20.0, 50.0), &Vec2::zero());
+    world.add_boid(&Vec2::new(80.0, 50.0), &Vec2::zero());
+    let attractor = world.add_attractor(&Vec2::new(30.0, 50.0), 1.0, 20.0, Falloff::Constant);
+    let repeller = world.add_attractor(&Vec2::new(90.0, 50.0), -1.0, 20.0, Falloff::Constant);
+    assert_eq!(world.num_attractors(), 2);
+    world.tick();
+    assert_eq!(world.get_boid(0).vel, Vec2::new(1.0, 0.0));
+    assert_eq!(world.get_boid(1).vel, Vec2::new(-1.0, 0.0));
+
+    // moved out of range of the first boid
+    assert!(world.move_attractor(attractor, &Vec2::new(90.0, 90.0)));
+    assert!(world.update_attractor(
+        repeller,
+        &Vec2::new(90.0, 50.0),
+        -2.0,
+        20.0,
+        Falloff::Linear
+    ));
+    let pos = world.get_boid(1).pos;
+    world.tick();
+    assert_eq!(world.get_boid(0).vel, Vec2::new(1.0, 0.0));
+    let expected = -2.0 * (1.0 - (90.0 - pos.x) / 20.0);
+    assert!((world.get_boid(1).vel.x - (-1.0 + expected)).abs() < 1e-12);
+
+    assert!(world.remove_attractor(attractor));
+    assert!(!world.remove_attractor(attractor));
+    assert!(!world.move_attractor(attractor, &Vec2::zero()));
+    assert!(!world.move_attractor(5, &Vec2::zero()));
+    assert_eq!(world.num_attractors(), 1);
+    // handles are not reused
+    assert_eq!(
+        world.add_attractor(&Vec2::zero(), 1.0, 1.0, Falloff::Smooth),
+        2
+    );
+
+    // the brute force tick steers the same way
+    let mut brute_force = World::new(
+        &Vec2::new(100.0, 100.0),
+        15.0,
+        5.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        10.0,
+        0.0,
+    );
+    brute_force.add_boid(&Vec2::new(80.0, 50.0), &Vec2::zero());
+    brute_force.add_attractor(&Vec2::new(90.0, 50.0), -1.0, 20.0, Falloff::Constant);
+    brute_force.tick_brute_force();
+    assert_eq!(brute_force.get_boid(0).vel, Vec2::new(-1.0, 0.0));
+}
+
+#[test]
+fn test_world_remove_boid() {
+    let mut world = World::new(
+        &Vec2::new(100.0, 100.0),
+        15.0,
+        5.0,
+        0.05,
+        0.05,
+        0.0005,
+        40.0,
+        0.2,
+        6.0,
+        2.0,
+    );
+    for i in 0..5 {
+        world.add_boid(&Vec2::new(i as f64 * 10.0, 50.0), &Vec2::zero());
+    }
+    assert!(world.remove_boid(1));
+    assert!(!world.remove_boid(1));
+    assert!(!world.remove_boid(5));
+    assert!(!world.has_boid(1));
+    assert_eq!(world.num_boids(), 4);
+    // the other ids still point at the same boids
+    for id in [0, 2, 3, 4] {
+        assert!(world.has_boid(id));
+        assert_eq!(world.get_boid(id).pos, Vec2::new(id as f64 * 10.0, 50.0));
+    }
+    let xs: Vec<f64> = world.boid_data().iter().step_by(4).copied().collect();
+    assert_eq!(xs, vec![0.0, 20.0, 30.0, 40.0]);
+    // removed boids are no one's neighbour once the
+    // index is rebuilt, which waits for the next query
+    assert_eq!(world.index_len(), 5);
+    assert!(world.quadtree_stats().is_some());
+    assert_eq!(world.index_len(), 4);
+    assert_eq!(world.add_boid(&Vec2::zero(), &Vec2::zero()), 5);
+    assert_eq!(world.index_len(), 5);
+
+    for id in [0, 2, 3, 4, 5] {
+        assert!(world.remove_boid(id));
+    }
+    assert_eq!(world.num_boids(), 0);
+    world.tick();
+    world.add_boid(&Vec2::new(50.0, 50.0), &Vec2::zero());
+    world.tick();
+    assert_eq!(world.num_boids(), 1);
+}
+
+#[test]
+fn test_world_pointer() {
+    // only the pointer steers
+    let mut world = World::new(
+        &Vec2::new(200.0, 200.0),
+        15.0,
+        5.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        10.0,
+        0.0,
+    );
+    world.pointer_radius = 20.0;
+    world.pointer_strength = 2.0;
+    world.add_boid(&Vec2::new(40.0, 50.0), &Vec2::zero());
+    world.set_pointer(&Vec2::new(50.0, 50.0), PointerMode::Attract);
+    world.tick();
+    // linear falloff, half way to the radius
+    assert_eq!(world.get_boid(0).vel, Vec2::new(1.0, 0.0));
+
+    world.set_pointer(&Vec2::new(51.0, 50.0), PointerMode::Repel);
+    world.tick();
+    assert_eq!(world.get_boid(0).vel, Vec2::new(0.0, 0.0));
+
+    world.clear_pointer();
+    world.remove_boid(0);
+    world.paint_rate = 3;
+    world.min_speed = 1.0;
+    world.max_speed = 2.0;
+    let center = Vec2::new(100.0, 100.0);
+    world.set_pointer(&center, PointerMode::Paint);
+    world.tick();
+    world.tick();
+    world.clear_pointer();
+    world.tick();
+    assert_eq!(world.num_boids(), 6);
+    for id in 1..7 {
+        let boid = world.get_boid(id);
+        // painted, then moved by at most three ticks
+        assert!(boid.pos.distance(&center) <= world.pointer_radius + 3.0 * world.max_speed);
+    }
+
+    world.pointer_radius = 1000.0;
+    world.set_pointer(&center, PointerMode::Erase);
+    world.tick();
+    assert_eq!(world.num_boids(), 0);
+    assert!(world.boid_data().is_empty());
+}
+
+#[test]
+fn test_world_currents() {
+    // only the currents steer
+    let mut world = World::new(
+        &Vec2::new(100.0, 100.0),
+        15.0,
+        5.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        10.0,
+        0.0,
+    );
+    world.add_boid(&Vec2::new(25.0, 50.0), &Vec2::zero());
+    world.wind = Vec2::new(0.5, 0.0);
+    world.tick();
+    assert_eq!(world.get_boid(0).vel, Vec2::new(0.5, 0.0));
+
+    // 2x2 grid over the world, the current
+    // points down on the left and up on the right
+    assert!(!world.set_flow_field(2, 2, &[0.0; 6]));
+    assert!(world.set_flow_field(2, 2, &[0.0, 1.0, 0.0, -1.0, 0.0, 1.0, 0.0, -1.0]));
+    assert_eq!(world.flow_field_cols(), 2);
+    assert_eq!(world.flow_field_data().len(), 8);
+    world.wind = Vec2::zero();
+    let pos = world.get_boid(0).pos;
+    world.tick();
+    let expected = 1.0 - 2.0 * pos.x / 100.0;
+    assert_eq!(world.get_boid(0).vel, Vec2::new(0.5, expected));
+
+    assert!(world.set_curl_noise_field(11, 11, 0.02, 1.0, 3));
+    assert_eq!(world.flow_field_rows(), 11);
+    world.clear_flow_field();
+    assert_eq!(world.flow_field_cols(), 0);
+    assert!(world.flow_field_data().is_empty());
+    let vel = world.get_boid(0).vel;
+    world.tick();
+    assert_eq!(world.get_boid(0).vel, vel);
+}
+
+#[test]
+fn test_world_wander_noise() {
+    let new_world = |seed, reorder_interval| {
+        let mut world = World::new(
+            &Vec2::new(200.0, 200.0),
+            32.0,
+            8.0,
+            0.05,
+            0.05,
+            0.0005,
+            25.0,
+            0.2,
+            4.0,
+            1.0,
+        );
+        world.set_seed(seed);
+        world.reorder_interval = reorder_interval;
+        world.wander_strength = 0.3;
+        world.noise = 0.5;
+        for i in 0..20 {
+            for j in 0..20 {
+                world.add_boid(
+                    &Vec2::new(i as f64 * 10.0 + 5.0, j as f64 * 10.0 + 5.0),
+                    &Vec2::new(1.0, 0.0),
+                );
+            }
+        }
+        world
+    };
+    let mut a = new_world(1, 0);
+    let mut b = new_world(1, 0);
+    let mut c = new_world(2, 0);
+    // the random numbers go to boids by id, so
+    // sorting the flock does not change them
+    let mut sorted = new_world(1, 1);
+    for _ in 0..20 {
+        a.tick();
+        b.tick();
+        c.tick();
+        sorted.tick();
+    }
+    assert_eq!(a.boid_data(), b.boid_data());
+    assert_ne!(a.boid_data(), c.boid_data());
+    for (x, y) in a.boid_data().iter().zip(&sorted.boid_data()) {
+        assert!((x - y).abs() < 1e-9);
+    }
+
+    // with every other rule off, noise only turns
+    let mut world = World::new(
+        &Vec2::new(100.0, 100.0),
+        15.0,
+        5.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        10.0,
+        0.0,
+    );
+    world.noise = 0.2;
+    world.add_boid(&Vec2::new(50.0, 50.0), &Vec2::new(2.0, 0.0));
+    let mut prev = world.get_boid(0).vel;
+    for _ in 0..10 {
+        world.tick();
+        let vel = world.get_boid(0).vel;
+        assert!((vel.length() - 2.0).abs() < 1e-12);
+        assert!(vel.dot(&prev) / 4.0 >= f64::cos(0.1) - 1e-12);
+        prev = vel;
+    }
+
+    // wander alone steers by wander_strength
+    world.noise = 0.0;
+    world.wander_strength = 0.5;
+    let vel = world.get_boid(0).vel;
+    world.tick();
+    assert!(((world.get_boid(0).vel - vel).length() - 0.5).abs() < 1e-12);
+}
+
+#[test]
+fn test_world_leaders() {
+    // only the leader rules steer
+    let mut world = World::new(
+        &Vec2::new(400.0, 400.0),
+        60.0,
+        5.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        3.0,
+        0.0,
+    );
+    let leader = world.add_boid(&Vec2::new(100.0, 200.0), &Vec2::zero());
+    let follower = world.add_boid(&Vec2::new(60.0, 200.0), &Vec2::zero());
+    let loner = world.add_boid(&Vec2::new(100.0, 350.0), &Vec2::zero());
+    assert!(!world.set_leader(5, true));
+    assert!(!world.set_leader_path(leader, &[300.0, 200.0], false));
+    assert!(world.set_leader(leader, true));
+    assert!(world.set_leader(leader, true));
+    assert_eq!(world.leader_ids(), vec![leader]);
+    assert!(!world.set_leader_path(leader, &[300.0], false));
+    assert!(world.set_leader_path(leader, &[300.0, 200.0, 100.0, 200.0], true));
+
+    let mut reached = false;
+    for _ in 0..150 {
+        world.tick();
+        let pos = world.get_boid(leader).pos;
+        reached |= pos.distance(&Vec2::new(300.0, 200.0)) <= world.waypoint_radius;
+    }
+    assert!(reached);
+    // turned around for the next waypoint
+    assert!(world.get_boid(leader).vel.x < 0.0);
+    // the follower kept up and stays out of the way
+    let lead = world.get_boid(leader);
+    let rel = world.get_boid(follower).pos - lead.pos;
+    assert!(rel.length() < world.visible_range);
+    assert!(rel.dot(&lead.vel) < 0.0);
+    // too far to see the leader
+    assert_eq!(world.get_boid(loner).vel, Vec2::zero());
+
+    assert!(world.set_leader(leader, false));
+    assert!(!world.is_leader(leader));
+    world.set_leader(follower, true);
+    world.remove_boid(follower);
+    assert!(world.leader_ids().is_empty());
+}
+
+#[test]
+fn test_world_path() {
+    let mut world = World::new(
+        &Vec2::new(400.0, 400.0),
+        32.0,
+        8.0,
+        0.05,
+        0.05,
+        0.0005,
+        25.0,
+        0.2,
+        4.0,
+        1.0,
+    );
+    assert!(!world.set_path(&[100.0, 100.0], true, 20.0));
+    assert!(!world.set_path(&[100.0, 100.0, 300.0], true, 20.0));
+    // clockwise square around the middle
+    let corners = [100.0, 100.0, 300.0, 100.0, 300.0, 300.0, 100.0, 300.0];
+    assert!(world.set_path(&corners, true, 20.0));
+    // alignment adds the average velocity of the neighbours,
+    // the flock needs a firmer pull to take the corners
+    world.path_factor = 0.2;
+    for i in 0..20 {
+        world.add_boid(
+            &Vec2::new(150.0 + i as f64 * 5.0, 150.0 + (i % 4) as f64 * 10.0),
+            &Vec2::new(2.0, 1.0),
+        );
+    }
+    for _ in 0..400 {
+        world.tick();
+    }
+    // the flocking rules still push boids around,
+    // but all of them are near the path and on the move
+    // clockwise along it
+    let path = world.path.clone().unwrap();
+    for id in 0..world.num_boids() {
+        let boid = world.get_boid(id);
+        let (point, dir) = path.project(boid.pos);
+        assert!(point.distance(&boid.pos) < 40.0);
+        assert!(boid.vel.dot(&dir) > 0.0);
+    }
+
+    world.clear_path();
+    assert!(world.path.is_none());
+}
+
+#[test]
+fn test_world_max_force() {
+    let mut world = World::new(
+        &Vec2::new(100.0, 100.0),
+        15.0,
+        5.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        10.0,
+        0.0,
+    );
+    world.add_boid(&Vec2::new(50.0, 50.0), &Vec2::zero());
+    let sep = Vec2::new(3.0, 0.0);
+    let align = Vec2::new(0.0, 4.0);
+    let cohesion = Vec2::new(-1.0, 0.0);
+    let steering = [sep, align, cohesion];
+
+    // unlimited by default
+    assert_eq!(world.acceleration(0, &steering), Vec2::new(2.0, 4.0));
+    // the sum is truncated
+    world.max_force = 2.0;
+    let accel = world.acceleration(0, &steering);
+    assert!((accel.length() - 2.0).abs() < 1e-12);
+    assert!((accel.x * 2.0 - accel.y).abs() < 1e-12);
+    // separation takes its share first, alignment gets
+    // the rest and cohesion none
+    world.max_force = 5.0;
+    world.prioritized = true;
+    assert_eq!(world.acceleration(0, &steering), Vec2::new(3.0, 2.0));
+    world.max_force = 2.0;
+    assert_eq!(world.acceleration(0, &steering), Vec2::new(2.0, 0.0));
+    // heavier boids turn slower
+    assert!(!world.set_boid_mass(0, 0.0));
+    assert!(!world.set_boid_mass(1, 2.0));
+    assert_eq!(world.boid_mass(1), None);
+    assert!(world.set_boid_mass(0, 4.0));
+    assert_eq!(world.boid_mass(0), Some(4.0));
+    assert_eq!(world.acceleration(0, &steering), Vec2::new(0.5, 0.0));
+
+    // an attractor pulls harder than max_force, so the
+    // boid speeds up by max_force / mass each tick
+    world.add_attractor(&Vec2::new(90.0, 50.0), 5.0, 100.0, Falloff::Constant);
+    for tick in 1..=4 {
+        world.tick();
+        assert_eq!(world.get_boid(0).vel, Vec2::new(0.5 * tick as f64, 0.0));
+    }
+}
+
+#[test]
+fn test_world_separation_falloff() {
+    let mut world = World::new(
+        &Vec2::new(100.0, 100.0),
+        15.0,
+        12.0,
+        1.0,
+        1.0,
+        1.0,
+        0.0,
+        0.0,
+        10.0,
+        0.0,
+    );
+    // uneven spacing, so every distance is different
+    for i in 0..10 {
+        for j in 0..10 {
+            world.add_boid(
+                &Vec2::new(
+                    i as f64 * 9.0 + j as f64 * 0.7,
+                    j as f64 * 8.0 + i as f64 * 0.3,
+                ),
+                &Vec2::zero(),
+            );
+        }
+    }
+    let mut neighbours = Neighbours::default();
+    for falloff in [
+        SeparationFalloff::Linear,
+        SeparationFalloff::Inverse,
+        SeparationFalloff::InverseSquare,
+        SeparationFalloff::Smooth,
+    ] {
+        world.separation_falloff = falloff;
+        for idx in 0..world.num_boids() {
+            let pos = world.boids.pos(idx);
+            world.query_neighbours(pos, world.protect_range, &mut neighbours);
+            let sums = neighbours.accumulate(
+                pos,
+                world.protect_range.powi(2),
+                0.0,
+                world.separation_falloff,
+            );
+            // summed in a different order
+            let sep = world.separation(&sums, &world.params(idx));
+            let expected = world.separation_brute_force(idx, &world.params(idx));
+            assert!((sep - expected).length() <= 1e-9 * expected.length().max(1.0));
+        }
+    }
+}
+
+#[test]
+fn test_world_boid_params() {
+    let mut world = World::new(
+        &Vec2::new(100.0, 100.0),
+        15.0,
+        5.0,
+        1.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        2.0,
+        0.0,
+    );
+    let a = world.add_boid(&Vec2::new(40.0, 50.0), &Vec2::new(1.0, 0.0));
+    let b = world.add_boid(&Vec2::new(50.0, 50.0), &Vec2::new(1.0, 0.0));
+    assert!(!world.set_boid_param(2, BoidParam::MaxSpeed, 1.0));
+    assert_eq!(world.boid_param(2, BoidParam::MaxSpeed), None);
+    assert!(!world.set_boid_param(a, BoidParam::MaxSpeed, f64::NAN));
+    assert!(!world.clear_boid_param(2, BoidParam::MaxSpeed));
+    assert!(!world.set_boid_param(a, BoidParam::ProtectRange, -5.0));
+    assert!(!world.set_boid_param(a, BoidParam::MinSpeed, -1.0));
+    assert!(!world.set_boid_param(a, BoidParam::MinSpeed, 3.0));
+    assert!(world.set_boid_param(b, BoidParam::MinSpeed, 1.5));
+    assert!(!world.set_boid_param(b, BoidParam::MaxSpeed, 1.0));
+    assert!(world.clear_boid_param(b, BoidParam::MinSpeed));
+    // factors can be negative
+    assert!(world.set_boid_param(a, BoidParam::CohesionFactor, -0.5));
+    assert!(world.clear_boid_param(a, BoidParam::CohesionFactor));
+    assert!(world.boids.overrides[world.slots[a]].is_none());
+
+    // a shy boid keeps its distance, the other
+    // one does not see it as too close
+    assert!(world.set_boid_param(a, BoidParam::ProtectRange, 20.0));
+    assert!(world.set_boid_param(a, BoidParam::MaxSpeed, 0.5));
+    assert_eq!(world.boid_param(a, BoidParam::ProtectRange), Some(20.0));
+    assert_eq!(world.boid_param(b, BoidParam::ProtectRange), Some(5.0));
+    let mut brute_force = World::new(
+        &Vec2::new(100.0, 100.0),
+        15.0,
+        5.0,
+        1.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        2.0,
+        0.0,
+    );
+    for id in [a, b] {
+        let boid = world.get_boid(id);
+        brute_force.add_boid(&boid.pos, &boid.vel);
+    }
+    brute_force.set_boid_param(a, BoidParam::ProtectRange, 20.0);
+    brute_force.set_boid_param(a, BoidParam::MaxSpeed, 0.5);
+    world.tick();
+    brute_force.tick_brute_force();
+    // pushed back and held to its own speed limit
+    assert_eq!(world.get_boid(a).vel, Vec2::new(-0.5, 0.0));
+    assert_eq!(world.get_boid(b).vel, Vec2::new(1.0, 0.0));
+    for id in [a, b] {
+        assert_eq!(world.get_boid(id).pos, brute_force.get_boid(id).pos);
+    }
+
+    // overrides follow the boid when the flock is reordered
+    world.reorder_interval = 1;
+    world.tick();
+    assert_eq!(world.boid_param(a, BoidParam::MaxSpeed), Some(0.5));
+    assert_eq!(world.boid_param(b, BoidParam::MaxSpeed), Some(2.0));
+
+    assert!(world.clear_boid_param(a, BoidParam::MaxSpeed));
+    assert_eq!(world.boid_param(a, BoidParam::MaxSpeed), Some(2.0));
+    assert!(world.clear_boid_param(a, BoidParam::ProtectRange));
+    assert!(world.boids.overrides[world.slots[a]].is_none());
+}
+
+#[test]
+fn test_world_energy() {
+    let mut world = World::new(
+        &Vec2::new(100.0, 100.0),
+        15.0,
+        5.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        2.0,
+        0.0,
+    );
+    world.max_energy = 10.0;
+    let moving = world.add_boid(&Vec2::new(20.0, 20.0), &Vec2::new(2.0, 0.0));
+    let eating = world.add_boid(&Vec2::new(80.0, 80.0), &Vec2::zero());
+    let hungry = world.add_boid(&Vec2::new(50.0, 20.0), &Vec2::zero());
+    assert_eq!(world.boid_energy(moving), Some(10.0));
+    // nothing burns by default
+    world.tick();
+    assert_eq!(world.energy_stats().mean, 10.0);
+
+    world.metabolism = 1.0;
+    let food = world.add_food(&Vec2::new(80.0, 80.0), 5.0, 3.0);
+    assert_eq!(world.num_foods(), 1);
+    assert!(world.set_boid_energy(eating, 5.0));
+    assert!(world.set_boid_energy(hungry, 5.0));
+    assert!(!world.set_boid_energy(3, 5.0));
+    world.tick();
+    assert_eq!(world.boid_energy(moving), Some(8.0));
+    assert_eq!(world.boid_energy(eating), Some(8.0));
+    // full boids do not look for food, hungry ones
+    // head for it as hard as they are hungry
+    assert_eq!(world.get_boid(moving).vel, Vec2::new(2.0, 0.0));
+    assert_eq!(world.get_boid(eating).vel, Vec2::zero());
+    let vel = world.get_boid(hungry).vel;
+    let desired = (Vec2::new(80.0, 80.0) - Vec2::new(50.0, 20.0)).normalize() * 2.0;
+    assert!((vel - desired * 0.25).length() < 1e-12);
+
+    // eating is capped at max_energy
+    world.tick();
+    assert_eq!(world.boid_energy(eating), Some(10.0));
+
+    assert!(world.remove_food(food));
+    assert!(!world.remove_food(food));
+    world.set_boid_energy(moving, 0.5);
+    world.set_boid_energy(hungry, 10.0);
+    world.tick();
+    assert!(!world.has_boid(moving));
+    assert_eq!(world.boid_energy(moving), None);
+    let stats = world.energy_stats();
+    assert_eq!((stats.population, stats.starved), (2, 1));
+    assert_eq!(stats.max, 10.0);
+    world.tick();
+    assert_eq!(world.energy_stats().starved, 0);
+
+    // boids without energy die even when nothing burns it
+    world.metabolism = 0.0;
+    world.set_boid_energy(hungry, 0.0);
+    world.tick();
+    assert!(!world.has_boid(hungry));
+    assert_eq!(world.boid_energy(hungry), None);
+    assert_eq!(world.energy_stats().starved, 1);
+}
+
+#[test]
+fn test_world_lifecycle() {
+    let mut world = World::new(
+        &Vec2::new(100.0, 100.0),
+        15.0,
+        5.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        2.0,
+        0.0,
+    );
+    let a = world.add_boid(&Vec2::new(20.0, 50.0), &Vec2::zero());
+    let b = world.add_boid(&Vec2::new(80.0, 50.0), &Vec2::zero());
+    world.set_boid_param(b, BoidParam::AlignFactor, 0.5);
+    // nothing is born or dies by default
+    world.tick();
+    assert_eq!((world.births(), world.deaths()), (0, 0));
+    assert_eq!(world.boid_age(a), Some(1));
+
+    world.birth_rate = 1.0;
+    world.maturity = 2;
+    world.birth_cost = 10.0;
+    world.max_population = 5;
+    world.tick();
+    assert_eq!((world.births(), world.deaths()), (2, 0));
+    assert_eq!(world.num_boids(), 4);
+    assert_eq!(world.boid_energy(a), Some(90.0));
+    for (parent, child) in [(a, 2), (b, 3)] {
+        assert_eq!(world.boid_age(child), Some(0));
+        assert_eq!(world.boid_energy(child), Some(100.0));
+        let dist = world
+            .get_boid(child)
+            .pos
+            .distance(&world.get_boid(parent).pos);
+        assert!(dist <= 5.0 + 1e-12);
+        for param in PARAMS {
+            let value = world.boid_param(parent, param).unwrap();
+            let diff = world.boid_param(child, param).unwrap() - value;
+            assert!(diff.abs() <= value.abs() * 0.05 + 1e-12);
+        }
+        assert!(
+            world.boid_param(child, BoidParam::MinSpeed)
+                <= world.boid_param(child, BoidParam::MaxSpeed)
+        );
+    }
+    // mutations scale the world's values, so
+    // changing those still reaches the children
+    let range = world.boid_param(2, BoidParam::VisibleRange).unwrap();
+    assert_ne!(range, 15.0);
+    world.visible_range = 30.0;
+    assert_eq!(
+        world.boid_param(2, BoidParam::VisibleRange),
+        Some(range * 2.0)
+    );
+    world.visible_range = 15.0;
+
+    // the children are too young, and one parent
+    // fills the population
+    world.tick();
+    assert_eq!(world.births(), 1);
+    assert_eq!(world.num_boids(), 5);
+
+    // without mutation children copy their parent,
+    // a had one child more and cannot pay for another
+    world.mutation = 0.0;
+    world.max_population = 6;
+    assert_eq!(
+        (world.boid_energy(a), world.boid_energy(b)),
+        (Some(80.0), Some(90.0))
+    );
+    world.birth_cost = 85.0;
+    world.tick();
+    assert_eq!(world.births(), 1);
+    assert_eq!(world.boid_param(5, BoidParam::AlignFactor), Some(0.5));
+    assert_eq!(world.boid_param(5, BoidParam::MaxSpeed), Some(2.0));
+
+    // the parents die first
+    world.birth_rate = 0.0;
+    world.max_age = 5;
+    world.tick();
+    assert_eq!((world.births(), world.deaths()), (0, 2));
+    assert!(!world.has_boid(a) && !world.has_boid(b));
+    assert_eq!(world.boid_age(a), None);
+    assert_eq!(world.num_boids(), 4);
+}
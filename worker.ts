@@ -0,0 +1,74 @@
+// runs the threaded build of World off the main thread,
+// browsers do not let the main thread wait on other threads
+import type * as Boids from "./pkg/boids.js";
+import {
+    createWorld,
+    setBackend,
+    setCurrents,
+    setPath,
+    setPointer,
+    tickWorld,
+    WasmModule,
+    WorkerRequest,
+    WorkerResponse,
+} from "./simulation.js";
+
+type ThreadedModule = WasmModule & {
+    initThreadPool(numThreads: number): Promise<void>;
+};
+
+// not a literal, so tsc does not need the threaded build to exist
+const THREADED_PKG: string = "./pkg-threads/boids.js";
+
+let scope = self as unknown as Worker;
+let wasm: ThreadedModule = undefined;
+let world: Boids.World = undefined;
+
+function respond(response: WorkerResponse, transfer: Transferable[] = []) {
+    scope.postMessage(response, transfer);
+}
+
+async function init(request: Extract<WorkerRequest, { type: "init" }>) {
+    try {
+        wasm = (await import(THREADED_PKG)) as ThreadedModule;
+        await wasm.default();
+        if (!wasm.threads_enabled()) {
+            throw new Error("pkg-threads was built without wasm-threads");
+        }
+        let threads = navigator.hardwareConcurrency;
+        await wasm.initThreadPool(threads);
+        world = createWorld(wasm, request.settings, request.boids);
+        respond({ type: "ready", threads });
+    } catch (err) {
+        respond({ type: "error", message: String(err) });
+    }
+}
+
+scope.onmessage = (event: MessageEvent<WorkerRequest>) => {
+    let request = event.data;
+    switch (request.type) {
+        case "init":
+            init(request);
+            break;
+        case "tick": {
+            let result = tickWorld(world);
+            respond({ type: "tick", ...result }, [result.boids.buffer]);
+            break;
+        }
+        case "set":
+            world[request.name] = request.value;
+            break;
+        case "backend":
+            setBackend(wasm, world, request.grid);
+            break;
+        case "pointer":
+            setPointer(wasm, world, request.pointer);
+            break;
+        case "currents":
+            setCurrents(wasm, world, request.currents);
+            break;
+        case "path":
+            setPath(world, request.path);
+            break;
+    }
+};